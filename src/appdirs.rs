@@ -0,0 +1,50 @@
+use std::path::PathBuf;
+
+use crate::yugioh::ImageSize;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppDirs {
+    pub appdata: PathBuf,
+    pub cache: PathBuf,
+    pub small: PathBuf,
+    pub large: PathBuf,
+    pub cropped: PathBuf,
+}
+
+impl AppDirs {
+    // lays out and creates the directory tree under root
+    pub fn create(appdata: PathBuf) -> std::io::Result<Self> {
+        std::fs::create_dir_all(&appdata)?;
+        // then we make sure the cache directory for the program exists
+        let cache = appdata.join("cache");
+        std::fs::create_dir_all(&cache)?;
+        // then we make sure there is a folder for every image tier in the cache directory
+        let small = cache.join("small");
+        std::fs::create_dir_all(&small)?;
+        let large = cache.join("large");
+        std::fs::create_dir_all(&large)?;
+        let cropped = cache.join("cropped");
+        std::fs::create_dir_all(&cropped)?;
+        Ok(Self {
+            appdata,
+            cache,
+            small,
+            large,
+            cropped,
+        })
+    }
+
+    // the per user data directory the gui uses, shared tooling should default to the same place
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn default_location() -> Option<PathBuf> {
+        dirs::data_local_dir().map(|dir| dir.join("yugiohdeckbuilder"))
+    }
+
+    pub fn image_dir(&self, size: ImageSize) -> PathBuf {
+        match size {
+            ImageSize::Small => self.small.clone(),
+            ImageSize::Large => self.large.clone(),
+            ImageSize::Cropped => self.cropped.clone(),
+        }
+    }
+}
@@ -0,0 +1,567 @@
+// headless deck tooling on top of the same card data the gui uses, it works on local files so the web build leaves it out
+#![cfg_attr(target_arch = "wasm32", no_main)]
+#![cfg(not(target_arch = "wasm32"))]
+use std::{
+    path::PathBuf,
+    sync::{atomic::AtomicBool, Arc},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+use ygodeckbuilderproelite::{
+    bulk::{self, BulkOptions, BulkScope},
+    deck::count_cards,
+    diff, diskcache, loader,
+    progress::{Progress, ProgressUnit},
+    search, siding,
+    source::CardSource,
+    stats, validate, AppDirs, Format, NetworkPolicy, PriceVendor, SortOrder, SortingMode, SortingType, YugiohCard, YugiohCardSearchCriteria, YugiohDeck,
+};
+
+const USAGE: &str = "usage: ygodeck [--json] [--data <cards.json>] [--network offline|cache-first|refresh-stale|force-refresh] <command> [args]
+
+commands:
+  search <pattern> [--sort name|id] [--desc]   find cards by name, same wildcards as the gui
+  validate <deck> [--format tcg|ocg|goat]      check deck sizes, copy limits and the banlist, in the deck's own format by default
+  convert <deck> --to ydk|ydke|text [--out <file>]
+  price <deck> [--vendor <name>]               price every card in the deck
+  stats <deck>                                 section sizes and card type breakdown
+  info <deck>                                  name, author, tags, notes and card roles saved in a .ydk
+  diff <old deck> <new deck>                   card count changes per section, exits with 1 if the decks differ
+  siding <deck> [--matchup <name>]             the siding guide and any problems with the plans in a .ydk,
+                                               or the deck after siding for one matchup
+  download [--large] [--cropped] [--archetype <name> | --set <name or code> | --deck <deck>] [--rate <requests per second>]
+                                               fill the image cache for offline use, rerun to resume
+
+<deck> is a .ydk file, a file with a ydke:// url or a text list, or a ydke:// url itself
+
+requests go through YGO_HTTP_PROXY if set, with YGO_USER_AGENT, YGO_CONNECT_TIMEOUT and YGO_TIMEOUT in seconds,
+and trust the extra root certificates in YGO_CA_CERTS, a list of pem or der files";
+
+struct Args {
+    json: bool,
+    help: bool,
+    data: Option<PathBuf>,
+    positional: Vec<String>,
+    options: Vec<(String, String)>,
+    flags: Vec<String>,
+}
+
+impl Args {
+    fn parse() -> Result<Self, String> {
+        let mut args = Args {
+            json: false,
+            help: false,
+            data: None,
+            positional: Vec::new(),
+            options: Vec::new(),
+            flags: Vec::new(),
+        };
+        let mut raw = std::env::args().skip(1);
+        while let Some(arg) = raw.next() {
+            match arg.as_str() {
+                "--json" => args.json = true,
+                "--desc" | "--large" | "--cropped" => args.flags.push(arg),
+                "-h" | "--help" => args.help = true,
+                "--data" => args.data = Some(PathBuf::from(raw.next().ok_or("--data needs a path")?)),
+                _ if arg.starts_with("--") => {
+                    let value = raw.next().ok_or(format!("{} needs a value", arg))?;
+                    args.options.push((arg.trim_start_matches("--").to_owned(), value));
+                }
+                _ => args.positional.push(arg),
+            }
+        }
+        Ok(args)
+    }
+
+    fn option(&self, name: &str) -> Option<&str> {
+        self.options.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    fn positional(&self, index: usize, what: &str) -> Result<&str, String> {
+        self.positional.get(index).map(|s| s.as_str()).ok_or(format!("missing {}\n\n{}", what, USAGE))
+    }
+}
+
+fn main() {
+    let code = match run() {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("{}", e);
+            2
+        }
+    };
+    std::process::exit(code);
+}
+
+// everything on the command line is checked before the card data is loaded, so a typo fails right away
+enum Command {
+    Help,
+    Search {
+        pattern: String,
+        sorting: SortingMode,
+    },
+    Validate {
+        deck: String,
+        format: Option<Format>,
+    },
+    Convert {
+        deck: String,
+        to: DeckFormat,
+        out: Option<String>,
+    },
+    Price {
+        deck: String,
+        vendor: PriceVendor,
+    },
+    Stats {
+        deck: String,
+        vendor: PriceVendor,
+    },
+    Info {
+        deck: String,
+    },
+    Diff {
+        before: String,
+        after: String,
+    },
+    Siding {
+        deck: String,
+        matchup: Option<String>,
+    },
+    Download {
+        scope: DownloadScope,
+        large: bool,
+        cropped: bool,
+        requests_per_second: f32,
+    },
+}
+
+enum DeckFormat {
+    Ydk,
+    Ydke,
+    Text,
+}
+
+// a deck scope needs the cards to be loaded first
+enum DownloadScope {
+    Bulk(BulkScope),
+    Deck(String),
+}
+
+impl Command {
+    fn parse(args: &Args) -> Result<Self, String> {
+        if args.help || args.positional.first().map(String::as_str) == Some("help") {
+            return Ok(Command::Help);
+        }
+        network_policy(args)?;
+        let deck = |index: usize, what: &str| args.positional(index, what).map(str::to_owned);
+        Ok(match args.positional(0, "command")? {
+            "search" => {
+                let stype = match args.option("sort").unwrap_or("name") {
+                    "name" => SortingType::Name,
+                    "id" => SortingType::Id,
+                    other => return Err(format!("unknown sort {:?}", other)),
+                };
+                let order = if args.flags.iter().any(|f| f == "--desc") { SortOrder::Dsc } else { SortOrder::Asc };
+                Command::Search {
+                    pattern: args.positional(1, "search pattern")?.to_owned(),
+                    sorting: SortingMode { stype, order },
+                }
+            }
+            "validate" => Command::Validate {
+                deck: deck(1, "deck")?,
+                format: match args.option("format").map(str::to_lowercase).as_deref() {
+                    None => None,
+                    Some("tcg") => Some(Format::Tcg),
+                    Some("ocg") => Some(Format::Ocg),
+                    Some("goat") => Some(Format::Goat),
+                    Some(other) => return Err(format!("unknown format {:?}", other)),
+                },
+            },
+            "convert" => Command::Convert {
+                deck: deck(1, "deck")?,
+                to: match args.option("to").ok_or("convert needs --to ydk|ydke|text")? {
+                    "ydk" => DeckFormat::Ydk,
+                    "ydke" => DeckFormat::Ydke,
+                    "text" => DeckFormat::Text,
+                    other => return Err(format!("unknown deck format {:?}", other)),
+                },
+                out: args.option("out").map(str::to_owned),
+            },
+            "price" => Command::Price {
+                deck: deck(1, "deck")?,
+                vendor: vendor(args)?,
+            },
+            "stats" => Command::Stats {
+                deck: deck(1, "deck")?,
+                vendor: vendor(args)?,
+            },
+            "info" => Command::Info { deck: deck(1, "deck")? },
+            "diff" => Command::Diff {
+                before: deck(1, "old deck")?,
+                after: deck(2, "new deck")?,
+            },
+            "siding" => Command::Siding {
+                deck: deck(1, "deck")?,
+                matchup: args.option("matchup").map(str::to_owned),
+            },
+            "download" => {
+                if !network_policy(args)?.allows_network() {
+                    return Err("download needs the network, it can not run with --network offline".to_owned());
+                }
+                let scope = if let Some(archetype) = args.option("archetype") {
+                    DownloadScope::Bulk(BulkScope::Archetype(archetype.to_owned()))
+                } else if let Some(set) = args.option("set") {
+                    DownloadScope::Bulk(BulkScope::Set(set.to_owned()))
+                } else if let Some(deck) = args.option("deck") {
+                    DownloadScope::Deck(deck.to_owned())
+                } else {
+                    DownloadScope::Bulk(BulkScope::All)
+                };
+                let requests_per_second = match args.option("rate") {
+                    Some(rate) => rate.parse().map_err(|_| format!("invalid rate {:?}", rate))?,
+                    None => bulk::DEFAULT_REQUESTS_PER_SECOND,
+                };
+                Command::Download {
+                    scope,
+                    large: args.flags.iter().any(|f| f == "--large"),
+                    cropped: args.flags.iter().any(|f| f == "--cropped"),
+                    requests_per_second,
+                }
+            }
+            other => return Err(format!("unknown command {:?}\n\n{}", other, USAGE)),
+        })
+    }
+}
+
+fn run() -> Result<i32, String> {
+    let args = Args::parse()?;
+    let command = Command::parse(&args)?;
+    if let Command::Help = command {
+        println!("{}", USAGE);
+        return Ok(0);
+    }
+    let cards = load_cards(&args)?;
+    match command {
+        Command::Help => Ok(0),
+        Command::Search { pattern, sorting } => {
+            let criteria = YugiohCardSearchCriteria { string: pattern };
+            let results: Vec<CardSummary> = search(&cards, &criteria, sorting).into_iter().map(|i| CardSummary::new(&cards[i])).collect();
+            if args.json {
+                print_json(&results)?;
+            } else {
+                for card in results {
+                    println!("{:>9}  {}  ({})", card.id, card.name, card.card_type);
+                }
+            }
+            Ok(0)
+        }
+        Command::Validate { deck, format } => {
+            let deck = load_deck(&deck, &cards)?;
+            let format = format.or(deck.metadata.format).unwrap_or(Format::Tcg);
+            let issues = validate(&deck, &cards, format);
+            let descriptions: Vec<String> = issues.iter().map(|issue| issue.describe(&cards)).collect();
+            if args.json {
+                print_json(&descriptions)?;
+            } else if descriptions.is_empty() {
+                println!("Deck is legal in {}", format.name());
+            } else {
+                for description in &descriptions {
+                    println!("{}", description);
+                }
+            }
+            Ok(if issues.is_empty() { 0 } else { 1 })
+        }
+        Command::Convert { deck, to, out } => {
+            let deck = load_deck(&deck, &cards)?;
+            let output = match to {
+                DeckFormat::Ydk => deck.to_ydk(&cards),
+                DeckFormat::Ydke => deck.to_ydke(&cards) + "\n",
+                DeckFormat::Text => deck.to_text(&cards),
+            };
+            if let Some(out) = out {
+                std::fs::write(&out, output).map_err(|e| format!("Failed to write {}: {}", out, e))?;
+            } else {
+                print!("{}", output);
+            }
+            Ok(0)
+        }
+        Command::Price { deck, vendor } => {
+            let deck = load_deck(&deck, &cards)?;
+            let prices = stats::price_list(&deck, &cards, vendor);
+            if args.json {
+                print_json(&prices)?;
+            } else {
+                for card in &prices {
+                    println!("{}x {:<40} {:>8.2} {:>8.2}", card.count, card.name, card.price, card.total);
+                }
+                println!("Total ({}): {:.2}", vendor.name(), prices.iter().map(|card| card.total).sum::<f32>());
+            }
+            Ok(0)
+        }
+        Command::Stats { deck, vendor } => {
+            let deck = load_deck(&deck, &cards)?;
+            let stats = stats::deck_stats(&deck, &cards, vendor);
+            if args.json {
+                print_json(&stats)?;
+            } else {
+                for (name, section, list) in [
+                    ("Main", &stats.main, &deck.main_deck),
+                    ("Extra", &stats.extra, &deck.extra_deck),
+                    ("Side", &stats.side, &deck.side_deck),
+                ] {
+                    println!(
+                        "{}: {} cards ({} monsters, {} spells, {} traps, {} unique)",
+                        name,
+                        section.cards,
+                        section.monsters,
+                        section.spells,
+                        section.traps,
+                        count_cards(list).len()
+                    );
+                }
+                println!("Price ({}): {:.2}", stats.vendor.name(), stats.price);
+            }
+            Ok(0)
+        }
+        Command::Info { deck } => {
+            let deck = load_deck(&deck, &cards)?;
+            let metadata = &deck.metadata;
+            let info = DeckInfo {
+                name: metadata.name.clone(),
+                author: metadata.author.clone(),
+                format: metadata.format.map(|format| format.name()),
+                tags: metadata.tags.clone(),
+                notes: metadata.notes.clone(),
+                created: metadata.created,
+                modified: metadata.modified,
+                roles: metadata
+                    .cards_by_role()
+                    .into_iter()
+                    .map(|(role, list)| RoleSummary {
+                        role,
+                        cards: list.iter().map(|card| cards[*card].name.clone()).collect(),
+                    })
+                    .collect(),
+            };
+            if args.json {
+                print_json(&info)?;
+            } else {
+                let age = |time: Option<u64>| match time {
+                    Some(time) => {
+                        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|now| now.as_secs()).unwrap_or(0);
+                        format!("{} ago", diskcache::format_age(Duration::from_secs(now.saturating_sub(time))))
+                    }
+                    None => "unknown".to_owned(),
+                };
+                println!("Name: {}", if info.name.is_empty() { "-" } else { &info.name });
+                println!("Author: {}", if info.author.is_empty() { "-" } else { &info.author });
+                println!("Format: {}", info.format.unwrap_or("-"));
+                println!("Tags: {}", if info.tags.is_empty() { "-".to_owned() } else { info.tags.join(", ") });
+                println!("Created: {}", age(info.created));
+                println!("Modified: {}", age(info.modified));
+                if !info.notes.is_empty() {
+                    println!("Notes:");
+                    for line in info.notes.lines() {
+                        println!("  {}", line);
+                    }
+                }
+                for role in &info.roles {
+                    println!("{}: {}", role.role, role.cards.join(", "));
+                }
+            }
+            Ok(0)
+        }
+        Command::Diff { before, after } => {
+            let before = load_deck(&before, &cards)?;
+            let after = load_deck(&after, &cards)?;
+            let diffs = diff::diff(&before, &after);
+            if args.json {
+                let lines: Vec<DiffLine> = diffs
+                    .iter()
+                    .flat_map(|diff| {
+                        diff.changes.iter().map(|change| DiffLine {
+                            section: diff.section.name(),
+                            id: cards[change.card].id,
+                            name: cards[change.card].name.clone(),
+                            before: change.before,
+                            after: change.after,
+                        })
+                    })
+                    .collect();
+                print_json(&lines)?;
+            } else if diff::is_unchanged(&diffs) {
+                println!("The decks are the same");
+            } else {
+                print!("{}", diff::to_text(&diffs, &cards));
+            }
+            Ok(if diff::is_unchanged(&diffs) { 0 } else { 1 })
+        }
+        Command::Siding { deck: path, matchup } => {
+            let deck = load_deck(&path, &cards)?;
+            if let Some(matchup) = matchup {
+                let plan = deck
+                    .side_plans
+                    .iter()
+                    .find(|plan| plan.matchup.eq_ignore_ascii_case(&matchup))
+                    .ok_or(format!("no plan for {:?}", matchup))?;
+                print!("{}", plan.apply(&deck, &cards).to_text(&cards));
+                return Ok(if plan.issues(&deck).is_empty() { 0 } else { 1 });
+            }
+            let names = |section: &[usize]| section.iter().map(|card| cards[*card].name.clone()).collect();
+            let plans: Vec<PlanSummary> = deck
+                .side_plans
+                .iter()
+                .map(|plan| PlanSummary {
+                    matchup: plan.matchup.clone(),
+                    side_in: names(&plan.side_in),
+                    side_out: names(&plan.side_out),
+                    notes: plan.notes.clone(),
+                    problems: plan.issues(&deck).iter().map(|issue| issue.describe(&cards)).collect(),
+                })
+                .collect();
+            if args.json {
+                print_json(&plans)?;
+            } else {
+                let title = std::path::Path::new(&path).file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+                print!("{}", siding::siding_guide(&title, &deck, &cards));
+                for plan in &plans {
+                    for problem in &plan.problems {
+                        eprintln!("vs {}: {}", plan.matchup, problem);
+                    }
+                }
+            }
+            Ok(if plans.iter().all(|plan| plan.problems.is_empty()) { 0 } else { 1 })
+        }
+        Command::Download {
+            scope,
+            large,
+            cropped,
+            requests_per_second,
+        } => {
+            let scope = match scope {
+                DownloadScope::Bulk(scope) => scope,
+                DownloadScope::Deck(deck) => BulkScope::deck(&load_deck(&deck, &cards)?),
+            };
+            let options = BulkOptions {
+                scope,
+                large,
+                cropped,
+                requests_per_second,
+            };
+            let app_dirs = app_dirs()?;
+            let progress = Progress::new("Downloading images", ProgressUnit::Items);
+            let report = bulk::download(&cards, &options, &app_dirs, card_source()?.as_ref(), &progress, &AtomicBool::new(false));
+            if args.json {
+                print_json(&report)?;
+            } else {
+                println!(
+                    "{} downloaded, {} made from large images, {} already cached, {} failed",
+                    report.downloaded, report.derived, report.skipped, report.failed
+                );
+            }
+            Ok(if report.failed == 0 { 0 } else { 1 })
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct PlanSummary {
+    matchup: String,
+    side_in: Vec<String>,
+    side_out: Vec<String>,
+    notes: String,
+    problems: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct DeckInfo {
+    name: String,
+    author: String,
+    format: Option<&'static str>,
+    tags: Vec<String>,
+    notes: String,
+    // unix seconds
+    created: Option<u64>,
+    modified: Option<u64>,
+    roles: Vec<RoleSummary>,
+}
+
+#[derive(Serialize)]
+struct RoleSummary {
+    role: String,
+    cards: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct DiffLine {
+    section: &'static str,
+    id: u32,
+    name: String,
+    before: usize,
+    after: usize,
+}
+
+#[derive(Serialize)]
+struct CardSummary {
+    id: u32,
+    name: String,
+    card_type: String,
+}
+
+impl CardSummary {
+    fn new(card: &YugiohCard) -> Self {
+        Self {
+            id: card.id,
+            name: card.name.clone(),
+            card_type: card.card_type.clone(),
+        }
+    }
+}
+
+fn load_cards(args: &Args) -> Result<Vec<YugiohCard>, String> {
+    let progress = Progress::new("Loading card data", ProgressUnit::Bytes);
+    if let Some(data) = &args.data {
+        let text = std::fs::read_to_string(data).map_err(|e| format!("Failed to read {}: {}", data.display(), e))?;
+        return loader::parse_cards(&text, &progress).map_err(|e| e.to_string());
+    }
+    loader::load_cards(app_dirs()?.cache, network_policy(args)?, card_source()?, progress)
+        .map(|loaded| loaded.cards)
+        .map_err(|e| e.to_string())
+}
+
+fn load_deck(deck: &str, cards: &[YugiohCard]) -> Result<YugiohDeck, String> {
+    let text = if deck.starts_with("ydke://") {
+        deck.to_owned()
+    } else {
+        std::fs::read_to_string(deck).map_err(|e| format!("Failed to read {}: {}", deck, e))?
+    };
+    YugiohDeck::parse_any(&text, cards).map_err(|e| e.to_string())
+}
+
+fn app_dirs() -> Result<AppDirs, String> {
+    let root = AppDirs::default_location().ok_or("Could not find the local data directory, pass --data")?;
+    AppDirs::create(root).map_err(|e| e.to_string())
+}
+
+fn card_source() -> Result<Arc<dyn CardSource>, String> {
+    ygodeckbuilderproelite::source::from_env(&ygodeckbuilderproelite::http::HttpConfig::from_env()).map_err(|e| format!("{:#}", e))
+}
+
+fn network_policy(args: &Args) -> Result<NetworkPolicy, String> {
+    match args.option("network") {
+        Some(name) => NetworkPolicy::from_name(name).ok_or(format!("unknown network policy {:?}", name)),
+        None => Ok(NetworkPolicy::default()),
+    }
+}
+
+fn vendor(args: &Args) -> Result<PriceVendor, String> {
+    let name = args.option("vendor").unwrap_or("tcgplayer");
+    PriceVendor::from_name(name).ok_or(format!("unknown vendor {:?}", name))
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<(), String> {
+    println!("{}", serde_json::to_string_pretty(value).map_err(|e| e.to_string())?);
+    Ok(())
+}
@@ -0,0 +1,135 @@
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use crate::{
+    appdirs::AppDirs,
+    deck::YugiohDeck,
+    diskcache,
+    progress::{Progress, ProgressUnit},
+    source::{CardSource, RateLimited},
+    yugioh::{ImageSize, YugiohCard},
+};
+
+// ygoprodeck asks for no more than 20 requests a second, staying well under that leaves room for the gui
+pub const DEFAULT_REQUESTS_PER_SECOND: f32 = 10.0;
+const RATE_LIMIT_PAUSE: Duration = Duration::from_secs(30);
+const MAX_RATE_LIMIT_RETRIES: u32 = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BulkScope {
+    All,
+    Archetype(String),
+    Set(String),
+    Cards(Vec<usize>),
+}
+
+impl BulkScope {
+    // every distinct card in any section of the deck
+    pub fn deck(deck: &YugiohDeck) -> Self {
+        let mut cards: Vec<usize> = deck.main_deck.iter().chain(deck.extra_deck.iter()).chain(deck.side_deck.iter()).copied().collect();
+        cards.sort_unstable();
+        cards.dedup();
+        Self::Cards(cards)
+    }
+
+    // archetypes and sets match case insensitively, sets by either their name or their code prefix like "LOB"
+    pub fn select(&self, cards: &[YugiohCard]) -> Vec<usize> {
+        match self {
+            BulkScope::All => (0..cards.len()).collect(),
+            BulkScope::Archetype(archetype) => (0..cards.len()).filter(|i| cards[*i].archetype.eq_ignore_ascii_case(archetype)).collect(),
+            BulkScope::Set(set) => (0..cards.len())
+                .filter(|i| {
+                    cards[*i]
+                        .card_sets
+                        .iter()
+                        .flatten()
+                        .any(|card_set| card_set.set_name.eq_ignore_ascii_case(set) || card_set.set_code.split('-').next().map(|code| code.eq_ignore_ascii_case(set)).unwrap_or(false))
+                })
+                .collect(),
+            BulkScope::Cards(selection) => selection.iter().copied().filter(|i| *i < cards.len()).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BulkOptions {
+    pub scope: BulkScope,
+    pub large: bool,
+    pub cropped: bool,
+    pub requests_per_second: f32,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct BulkReport {
+    pub downloaded: usize,
+    // already in the disk cache, which is what makes an interrupted run resumable
+    pub skipped: usize,
+    pub failed: usize,
+    // small images made from already cached large ones instead of being downloaded
+    pub derived: usize,
+    pub cancelled: bool,
+}
+
+// fills the disk image cache for every selected card, one request at a time at no more than requests_per_second
+pub fn download(cards: &[YugiohCard], options: &BulkOptions, app_dirs: &AppDirs, source: &dyn CardSource, progress: &Progress, cancel: &AtomicBool) -> BulkReport {
+    // large images go first so the small ones can be made from them instead of being downloaded too
+    let mut sizes = vec![ImageSize::Small];
+    if options.large {
+        sizes.insert(0, ImageSize::Large);
+    }
+    if options.cropped {
+        sizes.push(ImageSize::Cropped);
+    }
+    let selection = options.scope.select(cards);
+    let images: Vec<_> = sizes.iter().flat_map(|size| selection.iter().map(move |i| cards[*i].card_image.get(*size))).collect();
+    progress.start("Downloading images", ProgressUnit::Items, Some(images.len() as u64));
+    let interval = Duration::from_secs_f32(1.0 / options.requests_per_second.max(0.1));
+    let mut report = BulkReport::default();
+    let mut last_request: Option<Instant> = None;
+    for image in images {
+        if cancel.load(Ordering::Relaxed) {
+            report.cancelled = true;
+            break;
+        }
+        let path = diskcache::image_path(&app_dirs.image_dir(image.size), image.id);
+        if std::fs::metadata(&path).map(|m| m.len() > 0).unwrap_or(false) {
+            report.skipped += 1;
+            progress.advance(1);
+            continue;
+        }
+        if image.size == ImageSize::Small && diskcache::derive_small(app_dirs, image.id).is_ok() {
+            report.derived += 1;
+            progress.advance(1);
+            continue;
+        }
+        let mut attempts = 0;
+        let result = loop {
+            if let Some(last_request) = last_request {
+                std::thread::sleep(interval.saturating_sub(last_request.elapsed()));
+            }
+            last_request = Some(Instant::now());
+            match source.fetch_image(image.id, image.size, &image.url) {
+                Err(e) if attempts < MAX_RATE_LIMIT_RETRIES && e.is::<RateLimited>() => {
+                    attempts += 1;
+                    let pause = e.downcast_ref::<RateLimited>().and_then(|e| e.retry_after).unwrap_or(RATE_LIMIT_PAUSE);
+                    std::thread::sleep(pause);
+                }
+                result => break result,
+            }
+        };
+        let written = result.and_then(|bytes| Ok(diskcache::store(&path, &bytes)?));
+        match written {
+            Ok(()) => report.downloaded += 1,
+            Err(e) => {
+                eprintln!("Failed to download {} image {}: {}", image.size, image.id, e);
+                report.failed += 1;
+            }
+        }
+        progress.advance(1);
+    }
+    report
+}
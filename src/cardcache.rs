@@ -0,0 +1,152 @@
+use std::path::Path;
+
+use crate::{
+    progress::{Progress, ProgressUnit},
+    yugioh::YugiohCard,
+};
+
+const MAGIC: &[u8; 4] = b"YGOC";
+// bump this whenever YugiohCard or anything it contains changes shape, old caches are then ignored and rebuilt from json
+const SCHEMA_VERSION: u32 = 4;
+const HEADER_LEN: usize = 4 + 4 + 8;
+
+// layout: magic, schema version (u32 le), fnv-1a checksum of the payload (u64 le), bincode encoded Vec<YugiohCard>
+pub fn read(bin_path: &Path, json_path: &Path, progress: &Progress) -> Result<Vec<YugiohCard>, anyhow::Error> {
+    if is_older_than(bin_path, json_path) {
+        return Err(anyhow::anyhow!("Binary card cache is older than the card data"));
+    }
+    progress.start("Reading binary card cache", ProgressUnit::Bytes, None);
+    let bytes = std::fs::read(bin_path)?;
+    progress.advance(bytes.len() as u64);
+    if bytes.len() < HEADER_LEN || &bytes[0..4] != MAGIC {
+        return Err(anyhow::anyhow!("Binary card cache has an invalid header"));
+    }
+    let version = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+    if version != SCHEMA_VERSION {
+        return Err(anyhow::anyhow!("Binary card cache has schema version {}, expected {}", version, SCHEMA_VERSION));
+    }
+    let mut checksum = [0; 8];
+    checksum.copy_from_slice(&bytes[8..HEADER_LEN]);
+    let payload = &bytes[HEADER_LEN..];
+    if u64::from_le_bytes(checksum) != fnv1a(payload) {
+        return Err(anyhow::anyhow!("Binary card cache checksum does not match"));
+    }
+    let cards: Vec<YugiohCard> = bincode::deserialize(payload)?;
+    if cards.is_empty() {
+        return Err(anyhow::anyhow!("Binary card cache is empty"));
+    }
+    Ok(cards)
+}
+
+pub fn write(bin_path: &Path, cards: &[YugiohCard]) -> Result<(), anyhow::Error> {
+    let payload = bincode::serialize(cards)?;
+    let mut bytes = Vec::with_capacity(HEADER_LEN + payload.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&SCHEMA_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&fnv1a(&payload).to_le_bytes());
+    bytes.extend_from_slice(&payload);
+    // write to a temporary file first so a crash halfway through never leaves a truncated cache behind
+    let tmp_path = bin_path.with_extension("bin.tmp");
+    std::fs::write(&tmp_path, bytes)?;
+    std::fs::rename(tmp_path, bin_path)?;
+    Ok(())
+}
+
+fn is_older_than(a: &Path, b: &Path) -> bool {
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    match (modified(a), modified(b)) {
+        (Some(a), Some(b)) => a < b,
+        _ => false,
+    }
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::testcards::cards;
+
+    // a fresh cache file per test, next to a card data file that is older than it
+    fn cache(name: &str) -> (PathBuf, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("ygodeck-cardcache-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let json_path = dir.join("cards.json");
+        std::fs::write(&json_path, "{}").unwrap();
+        let bin_path = dir.join("cards.bin");
+        write(&bin_path, &cards()).unwrap();
+        (bin_path, json_path)
+    }
+
+    fn read_error(bin_path: &Path, json_path: &Path) -> String {
+        read(bin_path, json_path, &Progress::new("test", ProgressUnit::Bytes)).unwrap_err().to_string()
+    }
+
+    fn change(path: &Path, change: impl FnOnce(&mut Vec<u8>)) {
+        let mut bytes = std::fs::read(path).unwrap();
+        change(&mut bytes);
+        std::fs::write(path, bytes).unwrap();
+    }
+
+    #[test]
+    fn round_trip() {
+        let (bin_path, json_path) = cache("round_trip");
+        let read = read(&bin_path, &json_path, &Progress::new("test", ProgressUnit::Bytes)).unwrap();
+        assert_eq!(
+            read.iter().map(|card| (card.id, &card.name)).collect::<Vec<_>>(),
+            cards().iter().map(|card| (card.id, &card.name)).collect::<Vec<_>>()
+        );
+        assert!(!bin_path.with_extension("bin.tmp").exists());
+    }
+
+    #[test]
+    fn bad_magic() {
+        let (bin_path, json_path) = cache("bad_magic");
+        change(&bin_path, |bytes| bytes[0..4].copy_from_slice(b"GIF8"));
+        assert_eq!(read_error(&bin_path, &json_path), "Binary card cache has an invalid header");
+    }
+
+    #[test]
+    fn other_schema_version() {
+        let (bin_path, json_path) = cache("other_schema_version");
+        change(&bin_path, |bytes| bytes[4..8].copy_from_slice(&(SCHEMA_VERSION - 1).to_le_bytes()));
+        assert_eq!(
+            read_error(&bin_path, &json_path),
+            format!("Binary card cache has schema version {}, expected {}", SCHEMA_VERSION - 1, SCHEMA_VERSION)
+        );
+    }
+
+    #[test]
+    fn checksum_mismatch() {
+        let (bin_path, json_path) = cache("checksum_mismatch");
+        change(&bin_path, |bytes| *bytes.last_mut().unwrap() ^= 1);
+        assert_eq!(read_error(&bin_path, &json_path), "Binary card cache checksum does not match");
+    }
+
+    #[test]
+    fn truncated() {
+        let (bin_path, json_path) = cache("truncated");
+        change(&bin_path, |bytes| bytes.truncate(bytes.len() / 2));
+        assert_eq!(read_error(&bin_path, &json_path), "Binary card cache checksum does not match");
+        change(&bin_path, |bytes| bytes.truncate(HEADER_LEN - 1));
+        assert_eq!(read_error(&bin_path, &json_path), "Binary card cache has an invalid header");
+    }
+
+    #[test]
+    fn older_than_the_card_data() {
+        let (bin_path, json_path) = cache("older_than_the_card_data");
+        let json = std::fs::File::options().write(true).open(&json_path).unwrap();
+        json.set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(60)).unwrap();
+        assert_eq!(read_error(&bin_path, &json_path), "Binary card cache is older than the card data");
+    }
+}
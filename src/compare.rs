@@ -0,0 +1,141 @@
+use std::path::Path;
+
+use eframe::egui;
+use ygodeckbuilderproelite::{
+    diff::{self, SectionDiff},
+    YugiohCard, YugiohDeck,
+};
+
+use crate::{store::Store, versions::Version};
+
+const ADDED: egui::Color32 = egui::Color32::from_rgb(100, 200, 100);
+const REMOVED: egui::Color32 = egui::Color32::from_rgb(220, 100, 100);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Choice {
+    Current,
+    Version(Version),
+    // a pasted list or url, or the path of another deck file
+    Other,
+}
+
+impl Choice {
+    fn describe(&self) -> String {
+        match self {
+            Choice::Current => "Current deck".to_owned(),
+            Choice::Version(version) => version.describe(),
+            Choice::Other => "Another deck".to_owned(),
+        }
+    }
+}
+
+// the window that compares two versions of the open deck, or the open deck with any other deck
+pub struct DeckCompare {
+    choices: [Choice; 2],
+    others: [String; 2],
+    // what the diff was last worked out for, it is only worked out again when any of it changes
+    last: Option<([Choice; 2], [String; 2], YugiohDeck)>,
+    result: Result<Vec<SectionDiff>, String>,
+}
+
+impl DeckCompare {
+    pub fn new() -> Self {
+        Self {
+            choices: [Choice::Current, Choice::Current],
+            others: [String::new(), String::new()],
+            last: None,
+            result: Ok(Vec::new()),
+        }
+    }
+
+    // starts out comparing the newest saved version with the deck as it is now
+    pub fn reset(&mut self, versions: &[Version]) {
+        self.choices = [versions.first().cloned().map(Choice::Version).unwrap_or(Choice::Current), Choice::Current];
+        self.last = None;
+        self.result = Ok(Vec::new());
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, cards: &[YugiohCard], deck: &YugiohDeck, store: &Store, versions: &[Version]) {
+        egui::Grid::new("compare decks").num_columns(2).show(ui, |ui| {
+            for (side, label) in ["Old", "New"].into_iter().enumerate() {
+                ui.label(label);
+                ui.vertical(|ui| {
+                    let choice = &mut self.choices[side];
+                    egui::ComboBox::from_id_source(("compare choice", side)).selected_text(choice.describe()).width(200.).show_ui(ui, |ui| {
+                        ui.selectable_value(choice, Choice::Current, Choice::Current.describe());
+                        for version in versions {
+                            ui.selectable_value(choice, Choice::Version(version.clone()), version.describe());
+                        }
+                        ui.selectable_value(choice, Choice::Other, Choice::Other.describe());
+                    });
+                    if *choice == Choice::Other {
+                        ui.add(
+                            egui::TextEdit::multiline(&mut self.others[side])
+                                .desired_rows(3)
+                                .hint_text("a deck file, a ydke:// url or a pasted list"),
+                        );
+                    }
+                });
+                ui.end_row();
+            }
+        });
+        let key = (self.choices.clone(), self.others.clone(), deck.clone());
+        if self.last.as_ref() != Some(&key) {
+            self.result = self.work_out(cards, deck, store);
+            self.last = Some(key);
+        }
+        ui.separator();
+        let diffs = match &self.result {
+            Ok(diffs) => diffs,
+            Err(e) => {
+                ui.colored_label(egui::Color32::RED, e);
+                return;
+            }
+        };
+        if diff::is_unchanged(diffs) {
+            ui.label("The decks are the same");
+            return;
+        }
+        if ui.button("Copy as text").clicked() {
+            ui.output().copied_text = diff::to_text(diffs, cards);
+        }
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for diff in diffs.iter().filter(|diff| !diff.changes.is_empty()) {
+                ui.strong(format!("{} (+{} -{})", diff.section.name(), diff.added(), diff.removed()));
+                for change in &diff.changes {
+                    let color = if change.delta() > 0 { ADDED } else { REMOVED };
+                    ui.colored_label(color, format!("{:+} {}  ({} to {})", change.delta(), cards[change.card].name, change.before, change.after));
+                }
+                ui.add_space(4.);
+            }
+        });
+    }
+
+    fn work_out(&self, cards: &[YugiohCard], deck: &YugiohDeck, store: &Store) -> Result<Vec<SectionDiff>, String> {
+        let before = resolve(&self.choices[0], &self.others[0], cards, deck, store)?;
+        let after = resolve(&self.choices[1], &self.others[1], cards, deck, store)?;
+        Ok(diff::diff(&before, &after))
+    }
+}
+
+fn resolve(choice: &Choice, other: &str, cards: &[YugiohCard], deck: &YugiohDeck, store: &Store) -> Result<YugiohDeck, String> {
+    match choice {
+        Choice::Current => Ok(deck.clone()),
+        Choice::Version(version) => match store.read(&version.path) {
+            Some(text) => Ok(YugiohDeck::from_ydk(&text, cards)),
+            None => Err(format!("{} can not be read anymore", version.path.display())),
+        },
+        Choice::Other => {
+            let other = other.trim();
+            if other.is_empty() {
+                return Err("Paste a deck or enter the path of a deck file".to_owned());
+            }
+            // a single line that names a readable file is a path, anything else is the deck itself
+            let text = match other.lines().count() {
+                1 => store.read(Path::new(other)).unwrap_or_else(|| other.to_owned()),
+                _ => other.to_owned(),
+            };
+            YugiohDeck::parse_any(&text, cards).map_err(|e| e.to_string())
+        }
+    }
+}
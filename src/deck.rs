@@ -0,0 +1,444 @@
+use std::path::Path;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{metadata::DeckMetadata, siding::SidePlan, validation::MAIN_DECK_MAX, yugioh::YugiohCard};
+
+// other clients skip lines starting with this, the deck builder keeps whatever a .ydk can not hold in them
+const DATA_COMMENT: &str = "##";
+
+// a deck refers to cards by their index in the loaded card list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct YugiohDeck {
+    pub main_deck: Vec<usize>,
+    pub extra_deck: Vec<usize>,
+    pub side_deck: Vec<usize>,
+    pub side_plans: Vec<SidePlan>,
+    pub metadata: DeckMetadata,
+    pub been_loaded: bool,
+}
+
+impl YugiohDeck {
+    pub fn new(been_loaded: bool) -> Self {
+        Self {
+            main_deck: Vec::new(),
+            extra_deck: Vec::new(),
+            side_deck: Vec::new(),
+            side_plans: Vec::new(),
+            metadata: DeckMetadata::default(),
+            been_loaded,
+        }
+    }
+    pub fn from_file(path: impl AsRef<Path>, cards: &[YugiohCard]) -> Self {
+        // try to read the file, a missing or unreadable deck is just an empty one
+        if let Ok(text) = std::fs::read_to_string(path) {
+            Self::from_ydk(&text, cards)
+        } else {
+            Self::new(true)
+        }
+    }
+
+    pub fn from_ydk(text: &str, cards: &[YugiohCard]) -> Self {
+        let mut deck = Self::new(true);
+        let mut current_deck = DeckType::None;
+        for line in text.lines() {
+            // data comments are never cards or markers, even if their text happens to contain one
+            if line.starts_with(DATA_COMMENT) {
+                if let Some(plan) = SidePlan::from_comment(line, cards) {
+                    deck.side_plans.push(plan);
+                } else if let Some(metadata) = DeckMetadata::from_comment(line, cards) {
+                    deck.metadata = metadata;
+                }
+                continue;
+            }
+            // parse the line as a u32
+            let card_id = line.trim().parse::<u32>();
+            if let Ok(card_id) = card_id {
+                // get the index of the card in the cards vector
+                let card_index = cards.iter().position(|card| card.id == card_id);
+                if let Some(card_index) = card_index {
+                    // add the card to the deck
+                    if let Some(section) = deck.section_mut(current_deck) {
+                        section.push(card_index);
+                    }
+                } else {
+                    eprintln!("Card with id {} not found", card_id);
+                }
+            } else {
+                // match on the deck markers. if the line *contains* a deck marker, set the current deck to that deck
+                if line.contains("#main") {
+                    current_deck = DeckType::Main;
+                } else if line.contains("#extra") {
+                    current_deck = DeckType::Extra;
+                } else if line.contains("!side") {
+                    current_deck = DeckType::Side;
+                }
+            }
+        }
+        deck
+    }
+
+    pub fn to_ydk(&self, cards: &[YugiohCard]) -> String {
+        let mut text = String::from("#created by ygodeckbuilderproelite\n");
+        // roles of cards that left the deck are only kept until it is saved
+        let mut metadata = self.metadata.clone();
+        metadata.card_roles.retain(|card, _| self.count(*card) > 0);
+        if !metadata.is_empty() {
+            text.push_str(&metadata.to_comment(cards));
+            text.push('\n');
+        }
+        for plan in &self.side_plans {
+            text.push_str(&plan.to_comment(cards));
+            text.push('\n');
+        }
+        for (marker, section) in [("#main", &self.main_deck), ("#extra", &self.extra_deck), ("!side", &self.side_deck)] {
+            text.push_str(marker);
+            text.push('\n');
+            for card in section {
+                text.push_str(&cards[*card].id.to_string());
+                text.push('\n');
+            }
+        }
+        text
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>, cards: &[YugiohCard]) -> std::io::Result<()> {
+        std::fs::write(path, self.to_ydk(cards))
+    }
+
+    // ydke://<main>!<extra>!<side>! where every section is base64 of little endian u32 passcodes
+    pub fn from_ydke(url: &str, cards: &[YugiohCard]) -> Result<Self, anyhow::Error> {
+        let body = url.trim().strip_prefix("ydke://").ok_or_else(|| anyhow::anyhow!("Not a ydke url"))?;
+        let sections: Vec<&str> = body.split('!').collect();
+        if sections.len() < 3 {
+            return Err(anyhow::anyhow!("A ydke url needs a main, extra and side section"));
+        }
+        let mut deck = Self::new(true);
+        for (section, deck_type) in sections.iter().zip([DeckType::Main, DeckType::Extra, DeckType::Side]) {
+            let bytes = base64::decode(section)?;
+            // a url that was cut off somewhere, better to say so than to load part of a card
+            if bytes.len() % 4 != 0 {
+                return Err(anyhow::anyhow!("A ydke section has {} bytes, passcodes take 4 each", bytes.len()));
+            }
+            for passcode in bytes.chunks_exact(4) {
+                let card_id = u32::from_le_bytes([passcode[0], passcode[1], passcode[2], passcode[3]]);
+                if let Some(card_index) = cards.iter().position(|card| card.id == card_id) {
+                    deck.add_card(card_index, deck_type);
+                } else {
+                    eprintln!("Card with id {} not found", card_id);
+                }
+            }
+        }
+        Ok(deck)
+    }
+
+    pub fn to_ydke(&self, cards: &[YugiohCard]) -> String {
+        let encode = |section: &Vec<usize>| base64::encode(section.iter().flat_map(|card| cards[*card].id.to_le_bytes()).collect::<Vec<u8>>());
+        format!("ydke://{}!{}!{}!", encode(&self.main_deck), encode(&self.extra_deck), encode(&self.side_deck))
+    }
+
+    // a human readable list, one "<count> <name>" line per card under a header for every section
+    pub fn from_text(text: &str, cards: &[YugiohCard]) -> Result<Self, anyhow::Error> {
+        let mut deck = Self::new(true);
+        let mut current_deck = DeckType::Main;
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            match line.trim_end_matches(':').to_lowercase().as_str() {
+                "main" | "main deck" => current_deck = DeckType::Main,
+                "extra" | "extra deck" => current_deck = DeckType::Extra,
+                "side" | "side deck" => current_deck = DeckType::Side,
+                _ => {
+                    let find = |name: &str| cards.iter().position(|card| card.name.eq_ignore_ascii_case(name));
+                    // "3 Name", "3x Name" or just "Name", falling back to the whole line for names that start with a number
+                    let counted = line
+                        .split_once(' ')
+                        .and_then(|(count, name)| Some((count.trim_end_matches('x').parse::<usize>().ok()?, name.trim())))
+                        .and_then(|(count, name)| Some((count, find(name)?)));
+                    let (count, card_index) = match counted {
+                        Some(counted) => counted,
+                        None => (1, find(line).ok_or_else(|| anyhow::anyhow!("No card named {:?}", line))?),
+                    };
+                    // no section holds more cards than this, and a count like 99999999999 would never fit in memory
+                    if count > MAIN_DECK_MAX {
+                        return Err(anyhow::anyhow!("{} copies of {} are more than a deck can hold", count, cards[card_index].name));
+                    }
+                    for _ in 0..count {
+                        deck.add_card(card_index, current_deck);
+                    }
+                }
+            }
+        }
+        Ok(deck)
+    }
+
+    pub fn to_text(&self, cards: &[YugiohCard]) -> String {
+        let mut text = String::new();
+        for (header, section) in [("Main", &self.main_deck), ("Extra", &self.extra_deck), ("Side", &self.side_deck)] {
+            text.push_str(&format!("{}:\n", header));
+            for (card, count) in count_cards(section) {
+                text.push_str(&format!("{} {}\n", count, cards[card].name));
+            }
+        }
+        text
+    }
+
+    // reads a deck in whichever of the supported formats the text is in
+    pub fn parse_any(text: &str, cards: &[YugiohCard]) -> Result<Self, anyhow::Error> {
+        let trimmed = text.trim();
+        if trimmed.starts_with("ydke://") {
+            Self::from_ydke(trimmed, cards)
+        } else if trimmed.contains("#main") || trimmed.contains("#extra") || trimmed.contains("!side") {
+            Ok(Self::from_ydk(text, cards))
+        } else {
+            Self::from_text(text, cards)
+        }
+    }
+
+    pub fn section(&self, deck_type: DeckType) -> Option<&Vec<usize>> {
+        match deck_type {
+            DeckType::Main => Some(&self.main_deck),
+            DeckType::Extra => Some(&self.extra_deck),
+            DeckType::Side => Some(&self.side_deck),
+            DeckType::None => None,
+        }
+    }
+
+    pub fn section_mut(&mut self, deck_type: DeckType) -> Option<&mut Vec<usize>> {
+        match deck_type {
+            DeckType::Main => Some(&mut self.main_deck),
+            DeckType::Extra => Some(&mut self.extra_deck),
+            DeckType::Side => Some(&mut self.side_deck),
+            DeckType::None => None,
+        }
+    }
+
+    // how many copies of a card are in the deck across all sections
+    pub fn count(&self, card: usize) -> usize {
+        self.main_deck.iter().chain(self.extra_deck.iter()).chain(self.side_deck.iter()).filter(|c| **c == card).count()
+    }
+
+    pub fn add_card(&mut self, card: usize, deck_type: DeckType) {
+        if let Some(section) = self.section_mut(deck_type) {
+            section.push(card);
+        }
+    }
+
+    pub fn remove_card(&mut self, card: usize, deck_type: DeckType) -> bool {
+        if let Some(section) = self.section_mut(deck_type) {
+            if let Some(position) = section.iter().rposition(|c| *c == card) {
+                section.remove(position);
+                return true;
+            }
+        }
+        false
+    }
+
+    pub fn contains_card(&self, card: usize) -> DeckType {
+        if self.main_deck.contains(&card) {
+            DeckType::Main
+        } else if self.extra_deck.contains(&card) {
+            DeckType::Extra
+        } else if self.side_deck.contains(&card) {
+            DeckType::Side
+        } else {
+            DeckType::None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeckType {
+    None,
+    Main,
+    Extra,
+    Side,
+}
+
+impl DeckType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DeckType::None => "None",
+            DeckType::Main => "Main",
+            DeckType::Extra => "Extra",
+            DeckType::Side => "Side",
+        }
+    }
+}
+
+// "##<tag> <json>", a value for a .ydk that other clients will skip
+pub fn to_data_comment(tag: &str, value: &impl Serialize) -> String {
+    // the values stored this way are plain strings, numbers, lists and maps with number or string keys, which always serialize
+    format!("{}{} {}", DATA_COMMENT, tag, serde_json::to_string(value).unwrap_or_default())
+}
+
+// the value of a "##<tag> <json>" line, None for other tags and for json that does not fit
+pub fn from_data_comment<T: DeserializeOwned>(line: &str, tag: &str) -> Option<T> {
+    let json = line.strip_prefix(DATA_COMMENT)?.strip_prefix(tag)?.strip_prefix(' ')?;
+    serde_json::from_str(json).ok()
+}
+
+// where the card with a passcode is in the card list, data in files refers to cards by passcode since indices change with the list
+pub fn card_index(cards: &[YugiohCard], id: u32) -> Option<usize> {
+    cards.iter().position(|card| card.id == id)
+}
+
+// every distinct card in a section with how often it appears, in order of first appearance
+pub fn count_cards(section: &[usize]) -> Vec<(usize, usize)> {
+    let mut counts: Vec<(usize, usize)> = Vec::new();
+    for card in section {
+        if let Some(entry) = counts.iter_mut().find(|(c, _)| c == card) {
+            entry.1 += 1;
+        } else {
+            counts.push((*card, 1));
+        }
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testcards::{cards, deck, BLUE_EYES, COLORED_FISH, DARK_MAGICIAN, LACOODA, MIRROR_FORCE, POT_OF_GREED, ULTIMATE_DRAGON};
+
+    #[test]
+    fn ydk_from_other_clients() {
+        let cards = cards();
+        let text = "#created by Player\r\n#main\r\n89631139\r\n 46986414 \r\n12345\r\n#extra\r\n23995346\r\n!side\r\n55144522\r\n";
+        // unknown passcodes are skipped, the rest of the deck still loads
+        assert_eq!(YugiohDeck::from_ydk(text, &cards), deck(&[BLUE_EYES, DARK_MAGICIAN], &[ULTIMATE_DRAGON], &[POT_OF_GREED]));
+    }
+
+    #[test]
+    fn ydk_cards_before_any_marker_are_ignored() {
+        let cards = cards();
+        assert_eq!(YugiohDeck::from_ydk("89631139\n#main\n46986414\n", &cards).main_deck, vec![DARK_MAGICIAN]);
+    }
+
+    #[test]
+    fn ydk_round_trip() {
+        let cards = cards();
+        let saved = deck(&[BLUE_EYES, DARK_MAGICIAN, BLUE_EYES], &[ULTIMATE_DRAGON], &[POT_OF_GREED]);
+        let text = saved.to_ydk(&cards);
+        assert_eq!(text, "#created by ygodeckbuilderproelite\n#main\n89631139\n46986414\n89631139\n#extra\n23995346\n!side\n55144522\n");
+        assert_eq!(YugiohDeck::from_ydk(&text, &cards), saved);
+    }
+
+    #[test]
+    fn ydke_round_trip() {
+        let cards = cards();
+        let saved = deck(&[BLUE_EYES, BLUE_EYES, DARK_MAGICIAN], &[ULTIMATE_DRAGON], &[]);
+        let url = saved.to_ydke(&cards);
+        assert_eq!(
+            url,
+            format!(
+                "ydke://{}!{}!!",
+                base64::encode([89631139u32, 89631139, 46986414].iter().flat_map(|id| id.to_le_bytes()).collect::<Vec<u8>>()),
+                base64::encode(23995346u32.to_le_bytes())
+            )
+        );
+        assert_eq!(YugiohDeck::from_ydke(&url, &cards).unwrap(), saved);
+        // the same deck through every format
+        let text = YugiohDeck::from_ydk(&saved.to_ydk(&cards), &cards).to_text(&cards);
+        assert_eq!(YugiohDeck::from_text(&text, &cards).unwrap().to_ydke(&cards), url);
+    }
+
+    #[test]
+    fn ydke_that_can_not_be_read() {
+        let cards = cards();
+        assert!(YugiohDeck::from_ydke("https://example.com/deck", &cards).is_err());
+        assert!(YugiohDeck::from_ydke("ydke://o6lXBQ==!", &cards).is_err());
+        assert!(YugiohDeck::from_ydke("ydke://not base64!!!", &cards).is_err());
+        // five bytes, one passcode and a piece of the next
+        let cut_off = base64::encode([0xa3, 0xa9, 0x57, 0x05, 0x01]);
+        assert!(YugiohDeck::from_ydke(&format!("ydke://{}!!!", cut_off), &cards).is_err());
+        // unknown passcodes are skipped like in .ydk files
+        let unknown = base64::encode([12345u32.to_le_bytes(), 89631139u32.to_le_bytes()].concat());
+        assert_eq!(YugiohDeck::from_ydke(&format!("ydke://{}!!!", unknown), &cards).unwrap().main_deck, vec![BLUE_EYES]);
+    }
+
+    #[test]
+    fn text_counts_and_names() {
+        let cards = cards();
+        let text = "3x Blue-Eyes White Dragon\n2 dark magician\nMirror Force\n7 Colored Fish\n2 7 Colored Fish\n3-Hump Lacooda\n\nExtra Deck:\n1 Blue-Eyes Ultimate Dragon\nside\n 1x Pot of Greed \n";
+        let deck = YugiohDeck::from_text(text, &cards).unwrap();
+        assert_eq!(
+            deck.main_deck,
+            vec![
+                BLUE_EYES,
+                BLUE_EYES,
+                BLUE_EYES,
+                DARK_MAGICIAN,
+                DARK_MAGICIAN,
+                MIRROR_FORCE,
+                COLORED_FISH,
+                COLORED_FISH,
+                COLORED_FISH,
+                LACOODA
+            ]
+        );
+        assert_eq!(deck.extra_deck, vec![ULTIMATE_DRAGON]);
+        assert_eq!(deck.side_deck, vec![POT_OF_GREED]);
+    }
+
+    #[test]
+    fn text_with_an_unknown_name() {
+        let cards = cards();
+        let error = YugiohDeck::from_text("3 Blue-Eyes White Dragon\n2 Dark Magician Girl\n", &cards).unwrap_err();
+        assert_eq!(error.to_string(), "No card named \"2 Dark Magician Girl\"");
+    }
+
+    #[test]
+    fn text_with_too_many_copies() {
+        let cards = cards();
+        let error = YugiohDeck::from_text("99999999999 Blue-Eyes White Dragon\n", &cards).unwrap_err();
+        assert_eq!(error.to_string(), "99999999999 copies of Blue-Eyes White Dragon are more than a deck can hold");
+        assert!(YugiohDeck::from_text("61x Dark Magician\n", &cards).is_err());
+        // over the copy limit is still a list, validation reports it
+        assert_eq!(YugiohDeck::from_text("60 Dark Magician\n", &cards).unwrap().main_deck.len(), 60);
+    }
+
+    #[test]
+    fn text_round_trip_groups_copies() {
+        let cards = cards();
+        let saved = deck(&[COLORED_FISH, BLUE_EYES, COLORED_FISH], &[ULTIMATE_DRAGON], &[LACOODA]);
+        let text = saved.to_text(&cards);
+        assert_eq!(
+            text,
+            "Main:\n2 7 Colored Fish\n1 Blue-Eyes White Dragon\nExtra:\n1 Blue-Eyes Ultimate Dragon\nSide:\n1 3-Hump Lacooda\n"
+        );
+        assert_eq!(
+            YugiohDeck::from_text(&text, &cards).unwrap(),
+            deck(&[COLORED_FISH, COLORED_FISH, BLUE_EYES], &[ULTIMATE_DRAGON], &[LACOODA])
+        );
+    }
+
+    #[test]
+    fn parse_any_picks_the_format() {
+        let cards = cards();
+        let saved = deck(&[BLUE_EYES, DARK_MAGICIAN], &[ULTIMATE_DRAGON], &[POT_OF_GREED]);
+        for text in [saved.to_ydk(&cards), format!("  {}\n", saved.to_ydke(&cards)), saved.to_text(&cards)] {
+            assert_eq!(YugiohDeck::parse_any(&text, &cards).unwrap(), saved, "{}", text);
+        }
+        // a ydk without the created by line, as some sites export it
+        assert_eq!(YugiohDeck::parse_any("#main\n89631139\n", &cards).unwrap().main_deck, vec![BLUE_EYES]);
+        assert!(YugiohDeck::parse_any("ydke://broken", &cards).is_err());
+        assert!(YugiohDeck::parse_any("Not A Card", &cards).is_err());
+    }
+
+    #[test]
+    fn data_comments_only_match_their_own_tag() {
+        let line = to_data_comment("plan", &vec![1, 2]);
+        assert_eq!(line, "##plan [1,2]");
+        assert_eq!(from_data_comment::<Vec<u32>>(&line, "plan"), Some(vec![1, 2]));
+        assert_eq!(from_data_comment::<Vec<u32>>(&line, "meta"), None);
+        assert_eq!(from_data_comment::<Vec<u32>>("##planned [1,2]", "plan"), None);
+        assert_eq!(from_data_comment::<Vec<u32>>("##plan {\"not\":\"a list\"}", "plan"), None);
+        assert_eq!(from_data_comment::<Vec<u32>>("#plan [1,2]", "plan"), None);
+    }
+
+    #[test]
+    fn data_comments_never_switch_sections() {
+        let cards = cards();
+        let text = "#main\n89631139\n##note the rest goes to !side later\n46986414\n";
+        assert_eq!(YugiohDeck::from_ydk(text, &cards).main_deck, vec![BLUE_EYES, DARK_MAGICIAN]);
+    }
+}
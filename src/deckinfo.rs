@@ -0,0 +1,85 @@
+use std::time::Duration;
+
+use eframe::egui;
+use ygodeckbuilderproelite::{diskcache, metadata, DeckMetadata, Format, YugiohCard, YugiohDeck};
+
+use crate::store;
+
+// edits what the deck is called, who made it and what it is for, and lists the cards that were given a role
+pub struct DeckInfo {
+    // tags are edited as one comma separated line and only split up when it changes
+    tags: String,
+}
+
+impl DeckInfo {
+    pub fn new() -> Self {
+        Self { tags: String::new() }
+    }
+
+    pub fn reset(&mut self, metadata: &DeckMetadata) {
+        self.tags = metadata.tags.join(", ");
+    }
+
+    // whether the metadata differs from what it was before the window was drawn
+    pub fn ui(&mut self, ui: &mut egui::Ui, deck: &mut YugiohDeck, cards: &[YugiohCard]) -> bool {
+        let before = deck.metadata.clone();
+        let metadata = &mut deck.metadata;
+        egui::Grid::new("deck info").num_columns(2).show(ui, |ui| {
+            ui.label("Name");
+            ui.text_edit_singleline(&mut metadata.name);
+            ui.end_row();
+            ui.label("Author");
+            ui.text_edit_singleline(&mut metadata.author);
+            ui.end_row();
+            ui.label("Format");
+            egui::ComboBox::from_id_source("deck info format")
+                .selected_text(metadata.format.map(|format| format.name()).unwrap_or("Any"))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut metadata.format, None, "Any");
+                    for format in Format::ALL {
+                        ui.selectable_value(&mut metadata.format, Some(format), format.name());
+                    }
+                });
+            ui.end_row();
+            ui.label("Tags");
+            if ui.add(egui::TextEdit::singleline(&mut self.tags).hint_text("comma separated")).changed() {
+                metadata.tags = metadata::parse_tags(&self.tags);
+            }
+            ui.end_row();
+            ui.label("Created");
+            ui.label(age(metadata.created));
+            ui.end_row();
+            ui.label("Modified");
+            ui.label(age(metadata.modified));
+            ui.end_row();
+        });
+        ui.label("Notes");
+        ui.add(egui::TextEdit::multiline(&mut metadata.notes).desired_rows(4).desired_width(f32::INFINITY));
+        ui.separator();
+        let by_role = metadata.cards_by_role();
+        if by_role.is_empty() {
+            ui.label("Give cards roles like starter or extender from their detail window");
+        }
+        for (role, list) in by_role {
+            ui.strong(role);
+            ui.horizontal_wrapped(|ui| {
+                for card in list {
+                    // roles stay with cards that left the deck in case they come back, until the deck is saved
+                    if deck.count(card) > 0 {
+                        ui.label(&cards[card].name);
+                    } else {
+                        ui.weak(&cards[card].name).on_hover_text("Not in the deck");
+                    }
+                }
+            });
+        }
+        deck.metadata != before
+    }
+}
+
+fn age(time: Option<u64>) -> String {
+    match time {
+        Some(time) => format!("{} ago", diskcache::format_age(Duration::from_secs(store::unix_time().saturating_sub(time)))),
+        None => "Not saved yet".to_owned(),
+    }
+}
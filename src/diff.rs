@@ -0,0 +1,75 @@
+use crate::{
+    deck::{count_cards, DeckType, YugiohDeck},
+    yugioh::YugiohCard,
+};
+
+// how many copies of a card one section had in each deck
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CountChange {
+    pub card: usize,
+    pub before: usize,
+    pub after: usize,
+}
+
+impl CountChange {
+    pub fn delta(&self) -> isize {
+        self.after as isize - self.before as isize
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectionDiff {
+    pub section: DeckType,
+    // only the cards whose count changed, in the order they appear in the newer deck followed by the ones it dropped
+    pub changes: Vec<CountChange>,
+}
+
+impl SectionDiff {
+    pub fn added(&self) -> usize {
+        self.changes.iter().map(|change| change.after.saturating_sub(change.before)).sum()
+    }
+
+    pub fn removed(&self) -> usize {
+        self.changes.iter().map(|change| change.before.saturating_sub(change.after)).sum()
+    }
+}
+
+// what changed in every section going from before to after, sections that did not change have no changes
+pub fn diff(before: &YugiohDeck, after: &YugiohDeck) -> Vec<SectionDiff> {
+    [DeckType::Main, DeckType::Extra, DeckType::Side]
+        .into_iter()
+        .map(|section| {
+            let old = count_cards(before.section(section).map(Vec::as_slice).unwrap_or_default());
+            let new = count_cards(after.section(section).map(Vec::as_slice).unwrap_or_default());
+            let count = |counts: &[(usize, usize)], card: usize| counts.iter().find(|(c, _)| *c == card).map(|(_, count)| *count).unwrap_or(0);
+            let changes = new
+                .iter()
+                .map(|(card, _)| *card)
+                .chain(old.iter().map(|(card, _)| *card).filter(|card| count(&new, *card) == 0))
+                .map(|card| CountChange {
+                    card,
+                    before: count(&old, card),
+                    after: count(&new, card),
+                })
+                .filter(|change| change.before != change.after)
+                .collect();
+            SectionDiff { section, changes }
+        })
+        .collect()
+}
+
+pub fn is_unchanged(diffs: &[SectionDiff]) -> bool {
+    diffs.iter().all(|diff| diff.changes.is_empty())
+}
+
+// one "+2 Name" or "-1 Name" line per change under a header for every section that changed
+pub fn to_text(diffs: &[SectionDiff], cards: &[YugiohCard]) -> String {
+    let mut text = String::new();
+    for diff in diffs.iter().filter(|diff| !diff.changes.is_empty()) {
+        text.push_str(&format!("{}: +{} -{}\n", diff.section.name(), diff.added(), diff.removed()));
+        for change in &diff.changes {
+            text.push_str(&format!("{:+} {}\n", change.delta(), cards[change.card].name));
+        }
+    }
+    text
+}
@@ -0,0 +1,265 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{appdirs::AppDirs, loader::CardDataMeta, yugioh::ImageSize};
+
+// image files are <id>.cache in one directory per tier, their modification time doubles as the last time they were used
+const CACHE_EXTENSION: &str = "cache";
+// so when an image was downloaded is kept next to it in <id>.downloaded, as unix seconds
+const DOWNLOADED_EXTENSION: &str = "downloaded";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CacheLimits {
+    pub max_bytes: u64,
+    // images not used for this many days are pruned even when the cache is under max_bytes
+    pub max_age_days: Option<u32>,
+}
+
+impl Default for CacheLimits {
+    fn default() -> Self {
+        Self {
+            max_bytes: 1024 * 1024 * 1024,
+            max_age_days: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TierUsage {
+    pub size: ImageSize,
+    pub dir: PathBuf,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct CacheUsage {
+    pub tiers: Vec<TierUsage>,
+    pub card_data_bytes: u64,
+    pub card_data_age: Option<Duration>,
+    // when the server was last asked for newer card data, and when it last actually sent some
+    pub card_data_checked: Option<Duration>,
+    pub card_data_changed: Option<Duration>,
+}
+
+impl CacheUsage {
+    pub fn image_bytes(&self) -> u64 {
+        self.tiers.iter().map(|tier| tier.bytes).sum()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PruneReport {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+struct CachedFile {
+    path: PathBuf,
+    bytes: u64,
+    modified: SystemTime,
+}
+
+pub fn image_path(dir: &Path, id: u32) -> PathBuf {
+    dir.join(format!("{}.{}", id, CACHE_EXTENSION))
+}
+
+// writes under another name first and renames over the old file, so an interrupted write never leaves a truncated file in the cache
+pub fn replace(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let part = path.with_extension("part");
+    std::fs::write(&part, bytes)?;
+    std::fs::rename(part, path)
+}
+
+// replaces a cached image with one that was just downloaded
+pub fn store(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    replace(path, bytes)?;
+    record_download(path, SystemTime::now())
+}
+
+// when the image at path was downloaded, which is how the network policy tells how old it is
+pub fn downloaded_at(path: &Path) -> Option<SystemTime> {
+    let secs = std::fs::read_to_string(path.with_extension(DOWNLOADED_EXTENSION)).ok()?.trim().parse().ok()?;
+    Some(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+fn record_download(path: &Path, time: SystemTime) -> std::io::Result<()> {
+    let secs = time.duration_since(UNIX_EPOCH).map(|since| since.as_secs()).unwrap_or(0);
+    replace(&path.with_extension(DOWNLOADED_EXTENSION), secs.to_string().as_bytes())
+}
+
+// scales a cached large image down to a small one and stores it in the small tier, so small images never have to be downloaded
+pub fn derive_small(app_dirs: &AppDirs, id: u32) -> Result<Vec<u8>, anyhow::Error> {
+    let bytes = make_small(&std::fs::read(image_path(&app_dirs.large, id))?)?;
+    let small_path = image_path(&app_dirs.small, id);
+    replace(&small_path, &bytes)?;
+    // a small image is only as new as the large one it was made from
+    record_download(&small_path, downloaded_at(&image_path(&app_dirs.large, id)).unwrap_or_else(SystemTime::now))?;
+    Ok(bytes)
+}
+
+// the bytes of a small image scaled down from a large one
+pub fn make_small(large: &[u8]) -> Result<Vec<u8>, anyhow::Error> {
+    let (width, height) = ImageSize::Small.dimensions();
+    let small = image::load_from_memory(large)?.resize_exact(width, height, image::imageops::FilterType::Triangle);
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    small.write_to(&mut bytes, image::ImageOutputFormat::Jpeg(90))?;
+    Ok(bytes.into_inner())
+}
+
+pub fn usage(app_dirs: &AppDirs) -> CacheUsage {
+    let tiers = ImageSize::ALL
+        .into_iter()
+        .map(|size| {
+            let dir = app_dirs.image_dir(size);
+            let files = list(&dir);
+            TierUsage {
+                size,
+                files: files.len(),
+                bytes: files.iter().map(|file| file.bytes).sum(),
+                dir,
+            }
+        })
+        .collect();
+    let card_data = std::fs::metadata(app_dirs.cache.join("cards.json")).ok();
+    let meta = CardDataMeta::read(&app_dirs.cache);
+    CacheUsage {
+        card_data_checked: meta.checked_age(),
+        card_data_changed: meta.changed_age(),
+        tiers,
+        card_data_bytes: card_data.as_ref().map(|m| m.len()).unwrap_or(0),
+        card_data_age: card_data.and_then(|m| m.modified().ok()).and_then(|modified| modified.elapsed().ok()),
+    }
+}
+
+// marks a cached image as used right now so pruning keeps it around
+pub fn touch(path: &Path) {
+    if let Ok(file) = std::fs::File::options().write(true).open(path) {
+        let _ = file.set_modified(SystemTime::now());
+    }
+}
+
+pub fn clear(dir: &Path) -> PruneReport {
+    let mut report = PruneReport::default();
+    for file in list(dir) {
+        remove(&file, &mut report);
+    }
+    report
+}
+
+// drops everything older than the age limit, then the least recently used images until all tiers together fit in max_bytes
+pub fn prune(app_dirs: &AppDirs, limits: CacheLimits) -> PruneReport {
+    let mut report = PruneReport::default();
+    let mut files: Vec<CachedFile> = ImageSize::ALL.into_iter().flat_map(|size| list(&app_dirs.image_dir(size))).collect();
+    if let Some(days) = limits.max_age_days {
+        let max_age = Duration::from_secs(days as u64 * 24 * 60 * 60);
+        files.retain(|file| {
+            let expired = file.modified.elapsed().map(|age| age > max_age).unwrap_or(false);
+            if expired {
+                remove(file, &mut report);
+            }
+            !expired
+        });
+    }
+    let mut total: u64 = files.iter().map(|file| file.bytes).sum();
+    files.sort_by_key(|file| file.modified);
+    for file in files {
+        if total <= limits.max_bytes {
+            break;
+        }
+        total -= file.bytes;
+        remove(&file, &mut report);
+    }
+    report
+}
+
+fn list(dir: &Path) -> Vec<CachedFile> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().map(|e| e == CACHE_EXTENSION).unwrap_or(false))
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            Some(CachedFile {
+                path: entry.path(),
+                bytes: metadata.len(),
+                modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            })
+        })
+        .collect()
+}
+
+fn remove(file: &CachedFile, report: &mut PruneReport) {
+    let _ = std::fs::remove_file(file.path.with_extension(DOWNLOADED_EXTENSION));
+    if std::fs::remove_file(&file.path).is_ok() {
+        report.files += 1;
+        report.bytes += file.bytes;
+    }
+}
+
+pub fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    if secs < 60 * 60 {
+        format!("{} minutes", secs / 60)
+    } else if secs < 48 * 60 * 60 {
+        format!("{} hours", secs / (60 * 60))
+    } else {
+        format!("{} days", secs / (24 * 60 * 60))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::{self, NetworkPolicy};
+
+    fn app_dirs(name: &str) -> AppDirs {
+        let root = std::env::temp_dir().join(format!("ygodeck-diskcache-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&root);
+        AppDirs::create(root).unwrap()
+    }
+
+    fn set_modified(path: &Path, time: SystemTime) {
+        std::fs::File::options().write(true).open(path).unwrap().set_modified(time).unwrap();
+    }
+
+    #[test]
+    fn using_an_image_does_not_make_it_newer() {
+        let app_dirs = app_dirs("using_an_image_does_not_make_it_newer");
+        let path = image_path(&app_dirs.large, 89631139);
+        store(&path, b"image").unwrap();
+        // downloaded long ago, then shown today
+        let long_ago = SystemTime::now() - Duration::from_secs(100 * 24 * 60 * 60);
+        record_download(&path, long_ago).unwrap();
+        set_modified(&path, long_ago);
+        touch(&path);
+        assert!(std::fs::metadata(&path).unwrap().modified().unwrap().elapsed().unwrap() < Duration::from_secs(60));
+        assert_eq!(downloaded_at(&path), Some(UNIX_EPOCH + Duration::from_secs(long_ago.duration_since(UNIX_EPOCH).unwrap().as_secs())));
+        assert!(network::cached_age(&path).unwrap() > network::IMAGE_MAX_AGE);
+        assert!(!NetworkPolicy::RefreshStale.use_cached(&path, network::IMAGE_MAX_AGE));
+    }
+
+    #[test]
+    fn pruning_goes_by_last_use_and_takes_the_download_time_along() {
+        let app_dirs = app_dirs("pruning_goes_by_last_use_and_takes_the_download_time_along");
+        let unused = image_path(&app_dirs.small, 1);
+        let used = image_path(&app_dirs.small, 2);
+        store(&unused, b"unused").unwrap();
+        store(&used, b"used").unwrap();
+        set_modified(&unused, SystemTime::now() - Duration::from_secs(60));
+        touch(&used);
+        let report = prune(&app_dirs, CacheLimits { max_bytes: 4, max_age_days: None });
+        assert_eq!((report.files, report.bytes), (1, 6));
+        assert!(!unused.exists() && downloaded_at(&unused).is_none());
+        assert!(used.exists() && downloaded_at(&used).is_some());
+        // the download times are neither counted nor pruned as images
+        assert_eq!(usage(&app_dirs).image_bytes(), 4);
+    }
+}
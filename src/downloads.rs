@@ -0,0 +1,305 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Condvar, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use egui_extras::RetainedImage;
+use poll_promise::{Promise, Sender};
+use ygodeckbuilderproelite::{
+    diskcache,
+    network::{NetworkPolicy, IMAGE_MAX_AGE},
+    source::{CardSource, RateLimited},
+    AppDirs, CardImage, ImageSize, YugiohImage,
+};
+
+use crate::images::{Cancelled, ImageKey};
+
+pub const WORKERS: usize = 8;
+pub const MAX_PER_HOST: usize = 6;
+// how long a host that answered 429 without a Retry-After header is left alone
+const DEFAULT_RATE_LIMIT_PAUSE: Duration = Duration::from_secs(30);
+
+type ImageResult = Result<RetainedImage, anyhow::Error>;
+
+struct Job {
+    image: YugiohImage,
+    // the large version of the same card, small images can be made from it
+    large: YugiohImage,
+    host: String,
+    // the frame this image was last drawn or prefetched in, newer frames are served first
+    priority: u64,
+    // images on screen always go before images that are only being prefetched
+    visible: bool,
+    // jobs start out reading the disk cache and only take a host slot once they actually need the network
+    network: bool,
+    policy: NetworkPolicy,
+    sender: Sender<ImageResult>,
+}
+
+#[derive(Default)]
+struct Queue {
+    jobs: Vec<Job>,
+    active_hosts: HashMap<String, usize>,
+    // hosts that rate limited us and when we may talk to them again
+    paused_hosts: HashMap<String, Instant>,
+    frame: u64,
+    shutdown: bool,
+}
+
+impl Queue {
+    fn next_job(&mut self) -> Option<Job> {
+        let now = Instant::now();
+        self.paused_hosts.retain(|_, until| *until > now);
+        let index = self
+            .jobs
+            .iter()
+            .enumerate()
+            .filter(|(_, job)| !job.network || (self.active_hosts.get(&job.host).copied().unwrap_or(0) < MAX_PER_HOST && !self.paused_hosts.contains_key(&job.host)))
+            // max_by_key returns the last maximum, reversing keeps the oldest request first among equal priorities
+            .rev()
+            .max_by_key(|(_, job)| (job.visible, job.priority))
+            .map(|(index, _)| index)?;
+        let job = self.jobs.remove(index);
+        if job.network {
+            *self.active_hosts.entry(job.host.clone()).or_default() += 1;
+        }
+        Some(job)
+    }
+
+    // how long until a paused host opens up again, if any is paused
+    fn next_unpause(&self) -> Option<Duration> {
+        let now = Instant::now();
+        self.paused_hosts.values().map(|until| until.saturating_duration_since(now)).min()
+    }
+}
+
+struct Shared {
+    queue: Mutex<Queue>,
+    wake: Condvar,
+    source: Arc<dyn CardSource>,
+    app_dirs: AppDirs,
+    // download only large images and make the small ones from them, about half the traffic of downloading both
+    large_only: AtomicBool,
+}
+
+// a fixed pool of workers loading card images, from the disk cache when possible and from the card source otherwise
+pub struct Downloader {
+    shared: Arc<Shared>,
+}
+
+impl Downloader {
+    pub fn new(source: Arc<dyn CardSource>, app_dirs: AppDirs) -> Self {
+        let shared = Arc::new(Shared {
+            queue: Mutex::new(Queue::default()),
+            wake: Condvar::new(),
+            source,
+            app_dirs,
+            large_only: AtomicBool::new(false),
+        });
+        for i in 0..WORKERS {
+            let shared = shared.clone();
+            let spawned = std::thread::Builder::new().name(format!("image worker {}", i)).spawn(move || work(&shared));
+            if let Err(e) = spawned {
+                eprintln!("Failed to start image worker: {}", e);
+            }
+        }
+        Self { shared }
+    }
+
+    pub fn large_only(&self) -> bool {
+        self.shared.large_only.load(Ordering::Relaxed)
+    }
+
+    pub fn set_large_only(&self, large_only: bool) {
+        self.shared.large_only.store(large_only, Ordering::Relaxed);
+    }
+
+    pub fn request(&self, card_image: &CardImage, size: ImageSize, policy: NetworkPolicy) -> Promise<ImageResult> {
+        self.enqueue(card_image, size, policy, true)
+    }
+
+    // like request, but the image is only about to scroll into view so anything on screen is loaded first
+    pub fn prefetch(&self, card_image: &CardImage, size: ImageSize, policy: NetworkPolicy) -> Promise<ImageResult> {
+        self.enqueue(card_image, size, policy, false)
+    }
+
+    fn enqueue(&self, card_image: &CardImage, size: ImageSize, policy: NetworkPolicy, visible: bool) -> Promise<ImageResult> {
+        let (sender, promise) = Promise::new();
+        let image = card_image.get(size);
+        // the host of the url the database gave us, sources that rewrite image urls still mostly talk to one host per database
+        let host = reqwest::Url::parse(&image.url).ok().and_then(|url| url.host_str().map(str::to_owned)).unwrap_or_default();
+        let mut queue = self.shared.queue.lock().unwrap();
+        let priority = queue.frame;
+        queue.jobs.push(Job {
+            image: image.clone(),
+            large: card_image.large.clone(),
+            host,
+            priority,
+            visible,
+            network: policy == NetworkPolicy::ForceRefresh,
+            policy,
+            sender,
+        });
+        drop(queue);
+        self.shared.wake.notify_one();
+        promise
+    }
+
+    // call once per frame with every pending image that was drawn or prefetched, queued requests for anything else are cancelled
+    // requests a worker has already started are left to finish so the image at least ends up in the disk cache
+    pub fn retain(&self, visible: &HashSet<ImageKey>, prefetched: &HashSet<ImageKey>) {
+        let mut queue = self.shared.queue.lock().unwrap();
+        queue.frame += 1;
+        let frame = queue.frame;
+        let (keep, cancelled): (Vec<Job>, Vec<Job>) = std::mem::take(&mut queue.jobs).into_iter().partition(|job| {
+            let key = (job.image.id, job.image.size);
+            visible.contains(&key) || prefetched.contains(&key)
+        });
+        queue.jobs = keep;
+        for job in queue.jobs.iter_mut() {
+            job.priority = frame;
+            job.visible = visible.contains(&(job.image.id, job.image.size));
+        }
+        drop(queue);
+        for job in cancelled {
+            job.sender.send(Err(Cancelled.into()));
+        }
+    }
+
+    pub fn queued(&self) -> usize {
+        self.shared.queue.lock().unwrap().jobs.len()
+    }
+}
+
+impl Drop for Downloader {
+    fn drop(&mut self) {
+        self.shared.queue.lock().unwrap().shutdown = true;
+        self.shared.wake.notify_all();
+    }
+}
+
+fn work(shared: &Shared) {
+    loop {
+        let job = {
+            let mut queue = shared.queue.lock().unwrap();
+            loop {
+                if queue.shutdown {
+                    return;
+                }
+                if let Some(job) = queue.next_job() {
+                    break job;
+                }
+                queue = match queue.next_unpause() {
+                    Some(timeout) => shared.wake.wait_timeout(queue, timeout).unwrap().0,
+                    None => shared.wake.wait(queue).unwrap(),
+                };
+            }
+        };
+        if job.network {
+            let result = fetch(shared, &job);
+            let mut queue = shared.queue.lock().unwrap();
+            if let Some(active) = queue.active_hosts.get_mut(&job.host) {
+                *active -= 1;
+            }
+            match result {
+                Err(e) if e.is::<RateLimited>() => {
+                    // the image itself is fine, wait for the host to calm down and try again
+                    let pause = e.downcast_ref::<RateLimited>().and_then(|e| e.retry_after).unwrap_or(DEFAULT_RATE_LIMIT_PAUSE);
+                    eprintln!("{} is rate limiting image requests, pausing for {} seconds", job.host, pause.as_secs());
+                    queue.paused_hosts.insert(job.host.clone(), Instant::now() + pause);
+                    queue.jobs.push(job);
+                    drop(queue);
+                }
+                result => {
+                    drop(queue);
+                    // an outdated image is still better than none
+                    let result = result
+                        .or_else(|e| std::fs::read(diskcache::image_path(&shared.app_dirs.image_dir(job.image.size), job.image.id)).map_err(|_| e))
+                        .map_err(|e| anyhow::anyhow!("Failed to load image from {}: {}", shared.source.name(), e))
+                        .and_then(|bytes| decode(&job.image, bytes));
+                    job.sender.send(result);
+                }
+            }
+            // a host slot just opened up, which may unblock a job another worker skipped
+            shared.wake.notify_all();
+        } else if let Some(job) = from_cache(shared, job) {
+            if !job.policy.allows_network() {
+                job.sender
+                    .send(Err(anyhow::anyhow!("{} image {} is not cached and the network is disabled", job.image.size, job.image.id)));
+                continue;
+            }
+            // not cached, outdated, or the cached file was corrupt, back in line for the network
+            let mut queue = shared.queue.lock().unwrap();
+            queue.jobs.push(Job { network: true, ..job });
+            drop(queue);
+            shared.wake.notify_one();
+        }
+    }
+}
+
+// resolves the promise and returns None if the image was in the disk cache, hands the job back otherwise
+fn from_cache(shared: &Shared, job: Job) -> Option<Job> {
+    let cache_path = diskcache::image_path(&shared.app_dirs.image_dir(job.image.size), job.image.id);
+    if !job.policy.use_cached(&cache_path, IMAGE_MAX_AGE) {
+        return Some(job);
+    }
+    let bytes = match std::fs::read(&cache_path) {
+        Ok(bytes) => Some(bytes),
+        // a cached large image is as good as a cached small one
+        Err(_) if job.image.size == ImageSize::Small => diskcache::derive_small(&shared.app_dirs, job.image.id).ok(),
+        Err(_) => None,
+    };
+    let bytes = match bytes {
+        Some(bytes) => bytes,
+        None => return Some(job),
+    };
+    match RetainedImage::from_image_bytes(debug_name(&job.image), &bytes[..]) {
+        Ok(image) => {
+            diskcache::touch(&cache_path);
+            job.sender.send(Ok(image));
+            None
+        }
+        Err(_) => {
+            if let Err(e) = std::fs::remove_file(&cache_path) {
+                eprintln!("Failed to delete corrupted image {}: {}", cache_path.display(), e);
+            }
+            Some(job)
+        }
+    }
+}
+
+// the bytes of the image the job is for, in large only mode small images are made from a freshly downloaded large one
+fn fetch(shared: &Shared, job: &Job) -> Result<Vec<u8>, anyhow::Error> {
+    let app_dirs = &shared.app_dirs;
+    if job.image.size == ImageSize::Small && shared.large_only.load(Ordering::Relaxed) {
+        let large = shared.source.fetch_image(job.large.id, job.large.size, &job.large.url)?;
+        let small = diskcache::make_small(&large)?;
+        // the thumbnail is made either way, a full disk only means it has to be downloaded again next time
+        for (path, bytes) in [
+            (diskcache::image_path(&app_dirs.large, job.large.id), &large),
+            (diskcache::image_path(&app_dirs.small, job.image.id), &small),
+        ] {
+            if let Err(e) = diskcache::store(&path, bytes) {
+                eprintln!("Failed to write image to cache: {}", e);
+            }
+        }
+        return Ok(small);
+    }
+    let bytes = shared.source.fetch_image(job.image.id, job.image.size, &job.image.url)?;
+    if let Err(e) = diskcache::store(&diskcache::image_path(&app_dirs.image_dir(job.image.size), job.image.id), &bytes) {
+        eprintln!("Failed to write image to cache: {}", e);
+    }
+    Ok(bytes)
+}
+
+fn decode(image: &YugiohImage, bytes: Vec<u8>) -> ImageResult {
+    RetainedImage::from_image_bytes(debug_name(image), &bytes[..]).map_err(|e| anyhow::anyhow!("Failed to load image bytes: {}", e))
+}
+
+fn debug_name(image: &YugiohImage) -> String {
+    format!("{}:{}", image.size, image.id)
+}
@@ -0,0 +1,10 @@
+// fnv-1a, for names that are written to disk and have to come out the same on every run
+// std's hashers are only meant for hash maps, they may change between compiler versions and some are seeded randomly
+pub fn stable_hash(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
@@ -0,0 +1,665 @@
+use std::time::Duration;
+
+use instant::Instant;
+
+use crate::{
+    deck::{count_cards, DeckType, YugiohDeck},
+    metadata::DeckMetadata,
+    siding::SidePlan,
+    yugioh::YugiohCard,
+};
+
+// the same edit repeated within this long is undone in one go, like clicking + three times for a playset
+pub const GROUP_WINDOW: Duration = Duration::from_millis(1500);
+// the oldest entries are forgotten past this many
+pub const HISTORY_LIMIT: usize = 200;
+
+// the cards of the main, extra and side deck
+type Sections = [Vec<usize>; 3];
+
+// an edit the user makes to a deck
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeckEdit {
+    Add { card: usize, section: DeckType },
+    Remove { card: usize, section: DeckType },
+    // one copy from one section to another, like siding a card in
+    Move { card: usize, from: DeckType, to: DeckType },
+    // every copy of a card one place up or down among the distinct cards of its section
+    Reorder { card: usize, section: DeckType, up: bool },
+    // the whole deck replaced by an imported one, its side plans and metadata included
+    Import { deck: Box<YugiohDeck> },
+    // the name, notes, tags and card roles as they are after the edit
+    Info { metadata: Box<DeckMetadata> },
+    // every side plan as it is after the edit
+    Plans { plans: Vec<SidePlan> },
+}
+
+impl DeckEdit {
+    // how the edit shows up in the history list, times is how many of them were grouped together
+    pub fn describe(&self, cards: &[YugiohCard], times: usize) -> String {
+        let name = |card: &usize| cards.get(*card).map(|card| card.name.as_str()).unwrap_or("an unknown card");
+        let copies = if times > 1 { format!("{}x ", times) } else { String::new() };
+        match self {
+            DeckEdit::Add { card, section } => format!("Add {}{} to {}", copies, name(card), section.name()),
+            DeckEdit::Remove { card, section } => format!("Remove {}{} from {}", copies, name(card), section.name()),
+            DeckEdit::Move { card, from, to } => format!("Move {}{} from {} to {}", copies, name(card), from.name(), to.name()),
+            DeckEdit::Reorder { card, up, .. } => format!(
+                "Move {} {}{}",
+                name(card),
+                if *up { "up" } else { "down" },
+                if times > 1 { format!(" {} times", times) } else { String::new() }
+            ),
+            DeckEdit::Import { deck } => format!("Import {} cards", deck.main_deck.len() + deck.extra_deck.len() + deck.side_deck.len()),
+            DeckEdit::Info { .. } => "Edit the deck info".to_owned(),
+            DeckEdit::Plans { .. } => "Edit the side plans".to_owned(),
+        }
+    }
+
+    // whether this edit made right after other is undone together with it
+    fn groups_with(&self, other: &DeckEdit) -> bool {
+        match (self, other) {
+            (DeckEdit::Import { .. }, _) => false,
+            // typing into a field is one edit per key, they only make sense undone together
+            (DeckEdit::Info { .. }, DeckEdit::Info { .. }) | (DeckEdit::Plans { .. }, DeckEdit::Plans { .. }) => true,
+            _ => self == other,
+        }
+    }
+
+    // the steps that make this edit to deck as it is now, none if it would not change anything
+    fn changes(&self, deck: &YugiohDeck) -> Vec<Change> {
+        match self {
+            DeckEdit::Add { card, section } => match deck.section(*section) {
+                Some(cards) => vec![Change::Insert {
+                    section: *section,
+                    position: cards.len(),
+                    card: *card,
+                }],
+                None => Vec::new(),
+            },
+            // the last copy goes first, the same one remove_card takes
+            DeckEdit::Remove { card, section } => match deck.section(*section).and_then(|cards| cards.iter().rposition(|c| c == card)) {
+                Some(position) => vec![Change::Delete {
+                    section: *section,
+                    position,
+                    card: *card,
+                }],
+                None => Vec::new(),
+            },
+            DeckEdit::Move { card, from, to } => {
+                // the sections differ, so where the copy goes does not depend on where it was taken from
+                let removed = DeckEdit::Remove { card: *card, section: *from }.changes(deck);
+                let added = DeckEdit::Add { card: *card, section: *to }.changes(deck);
+                if from == to || removed.is_empty() || added.is_empty() {
+                    return Vec::new();
+                }
+                removed.into_iter().chain(added).collect()
+            }
+            DeckEdit::Reorder { card, section, up } => {
+                let cards = match deck.section(*section) {
+                    Some(cards) => cards,
+                    None => return Vec::new(),
+                };
+                let mut groups = count_cards(cards);
+                let index = match groups.iter().position(|(c, _)| c == card) {
+                    Some(index) => index,
+                    None => return Vec::new(),
+                };
+                let other = if *up { index.checked_sub(1) } else { Some(index + 1).filter(|other| *other < groups.len()) };
+                let other = match other {
+                    Some(other) => other,
+                    None => return Vec::new(),
+                };
+                groups.swap(index, other);
+                // copies of a card end up next to each other, which is how the deck list shows them anyway
+                let mut after = sections(deck);
+                after[section_index(*section)] = groups.into_iter().flat_map(|(card, count)| std::iter::repeat(card).take(count)).collect();
+                vec![Change::Replace { before: sections(deck), after }]
+            }
+            DeckEdit::Import { deck: imported } => {
+                let mut changes = Vec::new();
+                if sections(imported) != sections(deck) {
+                    changes.push(Change::Replace {
+                        before: sections(deck),
+                        after: sections(imported),
+                    });
+                }
+                changes.extend(
+                    DeckEdit::Info {
+                        metadata: Box::new(imported.metadata.clone()),
+                    }
+                    .changes(deck),
+                );
+                changes.extend(DeckEdit::Plans { plans: imported.side_plans.clone() }.changes(deck));
+                changes
+            }
+            DeckEdit::Info { metadata } if **metadata != deck.metadata => vec![Change::Metadata {
+                before: Box::new(deck.metadata.clone()),
+                after: metadata.clone(),
+            }],
+            DeckEdit::Plans { plans } if *plans != deck.side_plans => vec![Change::Plans {
+                before: deck.side_plans.clone(),
+                after: plans.clone(),
+            }],
+            DeckEdit::Info { .. } | DeckEdit::Plans { .. } => Vec::new(),
+        }
+    }
+}
+
+// the smallest steps an edit is made of, each of them can be taken back exactly
+#[derive(Debug, Clone)]
+enum Change {
+    Insert { section: DeckType, position: usize, card: usize },
+    Delete { section: DeckType, position: usize, card: usize },
+    Replace { before: Sections, after: Sections },
+    Metadata { before: Box<DeckMetadata>, after: Box<DeckMetadata> },
+    Plans { before: Vec<SidePlan>, after: Vec<SidePlan> },
+}
+
+impl Change {
+    fn apply(&self, deck: &mut YugiohDeck) {
+        match self {
+            Change::Insert { section, position, card } => {
+                if let Some(cards) = deck.section_mut(*section).filter(|cards| *position <= cards.len()) {
+                    cards.insert(*position, *card);
+                }
+            }
+            Change::Delete { section, position, .. } => {
+                if let Some(cards) = deck.section_mut(*section).filter(|cards| *position < cards.len()) {
+                    cards.remove(*position);
+                }
+            }
+            Change::Replace { after, .. } => {
+                let [main, extra, side] = after.clone();
+                deck.main_deck = main;
+                deck.extra_deck = extra;
+                deck.side_deck = side;
+            }
+            Change::Metadata { after, .. } => deck.metadata = (**after).clone(),
+            Change::Plans { after, .. } => deck.side_plans = after.clone(),
+        }
+    }
+
+    fn inverse(&self) -> Change {
+        match self {
+            Change::Insert { section, position, card } => Change::Delete {
+                section: *section,
+                position: *position,
+                card: *card,
+            },
+            Change::Delete { section, position, card } => Change::Insert {
+                section: *section,
+                position: *position,
+                card: *card,
+            },
+            Change::Replace { before, after } => Change::Replace {
+                before: after.clone(),
+                after: before.clone(),
+            },
+            Change::Metadata { before, after } => Change::Metadata {
+                before: after.clone(),
+                after: before.clone(),
+            },
+            Change::Plans { before, after } => Change::Plans {
+                before: after.clone(),
+                after: before.clone(),
+            },
+        }
+    }
+}
+
+fn sections(deck: &YugiohDeck) -> Sections {
+    [deck.main_deck.clone(), deck.extra_deck.clone(), deck.side_deck.clone()]
+}
+
+fn section_index(section: DeckType) -> usize {
+    match section {
+        DeckType::Main | DeckType::None => 0,
+        DeckType::Extra => 1,
+        DeckType::Side => 2,
+    }
+}
+
+pub struct HistoryEntry {
+    pub edit: DeckEdit,
+    // how many of the same edit were grouped into this entry
+    pub times: usize,
+    changes: Vec<Change>,
+    // when the entry was last added to, None once it may not be grouped with anything anymore
+    last: Option<Instant>,
+}
+
+impl HistoryEntry {
+    pub fn describe(&self, cards: &[YugiohCard]) -> String {
+        self.edit.describe(cards, self.times)
+    }
+}
+
+// every edit made to a deck since it was opened, so any of them can be undone and redone
+// edits have to go through apply for this to work, changing the deck behind its back makes undoing them undefined
+#[derive(Default)]
+pub struct DeckHistory {
+    undo: Vec<HistoryEntry>,
+    redo: Vec<HistoryEntry>,
+}
+
+impl DeckHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // makes the edit and records it, false if it changed nothing, like removing a card the deck does not have
+    pub fn apply(&mut self, deck: &mut YugiohDeck, edit: DeckEdit) -> bool {
+        let changes = edit.changes(deck);
+        if changes.is_empty() {
+            return false;
+        }
+        for change in &changes {
+            change.apply(deck);
+        }
+        self.redo.clear();
+        let now = Instant::now();
+        match self.undo.last_mut() {
+            Some(last) if edit.groups_with(&last.edit) && last.last.map(|at| now.duration_since(at) < GROUP_WINDOW).unwrap_or(false) => {
+                last.changes.extend(changes);
+                last.times += 1;
+                last.last = Some(now);
+            }
+            _ => {
+                self.undo.push(HistoryEntry {
+                    edit,
+                    times: 1,
+                    changes,
+                    last: Some(now),
+                });
+                if self.undo.len() > HISTORY_LIMIT {
+                    self.undo.remove(0);
+                }
+            }
+        }
+        true
+    }
+
+    pub fn undo(&mut self, deck: &mut YugiohDeck) -> bool {
+        let entry = match self.undo.pop() {
+            Some(entry) => entry,
+            None => return false,
+        };
+        for change in entry.changes.iter().rev() {
+            change.inverse().apply(deck);
+        }
+        self.redo.push(entry);
+        true
+    }
+
+    pub fn redo(&mut self, deck: &mut YugiohDeck) -> bool {
+        let mut entry = match self.redo.pop() {
+            Some(entry) => entry,
+            None => return false,
+        };
+        for change in &entry.changes {
+            change.apply(deck);
+        }
+        // a redone edit never groups with the next one
+        entry.last = None;
+        self.undo.push(entry);
+        true
+    }
+
+    // undoes or redoes until exactly applied entries are in effect, for clicking an entry in the history list
+    pub fn jump(&mut self, deck: &mut YugiohDeck, applied: usize) {
+        while self.undo.len() > applied && self.undo(deck) {}
+        while self.undo.len() < applied && self.redo(deck) {}
+    }
+
+    // the edits in effect, oldest first
+    pub fn applied(&self) -> &[HistoryEntry] {
+        &self.undo
+    }
+
+    // the undone edits that can be redone, the next one to redo last
+    pub fn undone(&self) -> &[HistoryEntry] {
+        &self.redo
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        siding::SidePlan,
+        testcards::{cards, deck, BLUE_EYES, DARK_MAGICIAN, POT_OF_GREED, ULTIMATE_DRAGON},
+    };
+
+    // applies the edit, then checks undo gives back the deck from before and redo the one from after
+    fn round_trip(mut start: YugiohDeck, edit: DeckEdit, expected: &YugiohDeck) {
+        let before = start.clone();
+        let mut history = DeckHistory::new();
+        assert!(history.apply(&mut start, edit));
+        assert_eq!(start, *expected);
+        assert!(history.undo(&mut start));
+        assert_eq!(start, before);
+        assert!(history.redo(&mut start));
+        assert_eq!(start, *expected);
+    }
+
+    // pretends the last edit was made long enough ago that the next one is not grouped with it
+    fn age_last(history: &mut DeckHistory) {
+        history.undo.last_mut().unwrap().last = Instant::now().checked_sub(GROUP_WINDOW * 2);
+    }
+
+    #[test]
+    fn add_round_trip() {
+        round_trip(
+            deck(&[BLUE_EYES], &[], &[]),
+            DeckEdit::Add {
+                card: DARK_MAGICIAN,
+                section: DeckType::Main,
+            },
+            &deck(&[BLUE_EYES, DARK_MAGICIAN], &[], &[]),
+        );
+    }
+
+    #[test]
+    fn remove_round_trip_keeps_position() {
+        round_trip(
+            deck(&[BLUE_EYES, DARK_MAGICIAN, BLUE_EYES, POT_OF_GREED], &[], &[]),
+            DeckEdit::Remove {
+                card: BLUE_EYES,
+                section: DeckType::Main,
+            },
+            &deck(&[BLUE_EYES, DARK_MAGICIAN, POT_OF_GREED], &[], &[]),
+        );
+    }
+
+    #[test]
+    fn move_round_trip() {
+        round_trip(
+            deck(&[BLUE_EYES, DARK_MAGICIAN], &[], &[POT_OF_GREED]),
+            DeckEdit::Move {
+                card: POT_OF_GREED,
+                from: DeckType::Side,
+                to: DeckType::Main,
+            },
+            &deck(&[BLUE_EYES, DARK_MAGICIAN, POT_OF_GREED], &[], &[]),
+        );
+    }
+
+    #[test]
+    fn reorder_round_trip() {
+        round_trip(
+            deck(&[BLUE_EYES, DARK_MAGICIAN, BLUE_EYES, POT_OF_GREED], &[], &[]),
+            DeckEdit::Reorder {
+                card: POT_OF_GREED,
+                section: DeckType::Main,
+                up: true,
+            },
+            &deck(&[BLUE_EYES, BLUE_EYES, POT_OF_GREED, DARK_MAGICIAN], &[], &[]),
+        );
+    }
+
+    #[test]
+    fn import_round_trip_includes_plans_and_metadata() {
+        let mut current = deck(&[BLUE_EYES], &[ULTIMATE_DRAGON], &[POT_OF_GREED]);
+        current.metadata.name = "Current".to_owned();
+        current.side_plans.push(SidePlan::new("Mirror".to_owned()));
+        let mut imported = deck(&[DARK_MAGICIAN], &[], &[]);
+        imported.metadata.name = "Imported".to_owned();
+        // the deck stays loaded whatever the imported one says
+        imported.been_loaded = false;
+        let mut expected = imported.clone();
+        expected.been_loaded = true;
+        round_trip(current, DeckEdit::Import { deck: Box::new(imported) }, &expected);
+    }
+
+    #[test]
+    fn info_and_plan_edits_after_an_import_are_undone_on_their_own() {
+        let mut current = deck(&[BLUE_EYES], &[], &[]);
+        current.metadata.name = "Current".to_owned();
+        let original = current.clone();
+        let mut imported = deck(&[DARK_MAGICIAN], &[], &[]);
+        imported.metadata.name = "Imported".to_owned();
+        let mut history = DeckHistory::new();
+        history.apply(&mut current, DeckEdit::Import { deck: Box::new(imported) });
+        let mut metadata = current.metadata.clone();
+        metadata.notes = "Go second".to_owned();
+        history.apply(&mut current, DeckEdit::Info { metadata: Box::new(metadata) });
+        let mut plan = SidePlan::new("Mirror".to_owned());
+        plan.side_in.push(POT_OF_GREED);
+        history.apply(&mut current, DeckEdit::Plans { plans: vec![plan] });
+        let edited = current.clone();
+        assert_eq!(
+            history.applied().iter().map(|entry| entry.describe(&cards())).collect::<Vec<_>>(),
+            ["Import 1 cards", "Edit the deck info", "Edit the side plans"]
+        );
+
+        history.undo(&mut current);
+        assert!(current.side_plans.is_empty());
+        assert_eq!(current.metadata.notes, "Go second");
+        history.undo(&mut current);
+        assert_eq!((current.metadata.name.as_str(), current.metadata.notes.as_str()), ("Imported", ""));
+        assert_eq!(current.main_deck, vec![DARK_MAGICIAN]);
+        history.undo(&mut current);
+        assert_eq!(current, original);
+        history.jump(&mut current, 3);
+        assert_eq!(current, edited);
+    }
+
+    #[test]
+    fn typing_into_the_deck_info_is_one_entry() {
+        let mut history = DeckHistory::new();
+        let mut current = deck(&[], &[], &[]);
+        for name in ["B", "Bl", "Blu", "Blue"] {
+            let mut metadata = current.metadata.clone();
+            metadata.name = name.to_owned();
+            history.apply(&mut current, DeckEdit::Info { metadata: Box::new(metadata) });
+        }
+        history.apply(
+            &mut current,
+            DeckEdit::Plans {
+                plans: vec![SidePlan::new("Mirror".to_owned())],
+            },
+        );
+        assert_eq!(history.applied().len(), 2);
+        history.undo(&mut current);
+        history.undo(&mut current);
+        assert_eq!(current, deck(&[], &[], &[]));
+    }
+
+    #[test]
+    fn grouped_entries_describe_how_many_times() {
+        let mut history = DeckHistory::new();
+        let mut current = deck(&[], &[], &[]);
+        history.apply(
+            &mut current,
+            DeckEdit::Add {
+                card: BLUE_EYES,
+                section: DeckType::Main,
+            },
+        );
+        assert_eq!(history.applied()[0].describe(&cards()), "Add Blue-Eyes White Dragon to Main");
+        history.apply(
+            &mut current,
+            DeckEdit::Add {
+                card: BLUE_EYES,
+                section: DeckType::Main,
+            },
+        );
+        assert_eq!(history.applied()[0].describe(&cards()), "Add 2x Blue-Eyes White Dragon to Main");
+    }
+
+    #[test]
+    fn edits_that_change_nothing_are_not_recorded() {
+        let mut history = DeckHistory::new();
+        let mut current = deck(&[BLUE_EYES], &[], &[]);
+        assert!(!history.apply(
+            &mut current,
+            DeckEdit::Remove {
+                card: DARK_MAGICIAN,
+                section: DeckType::Main
+            }
+        ));
+        assert!(!history.apply(
+            &mut current,
+            DeckEdit::Move {
+                card: BLUE_EYES,
+                from: DeckType::Main,
+                to: DeckType::Main
+            }
+        ));
+        assert!(!history.apply(
+            &mut current,
+            DeckEdit::Reorder {
+                card: BLUE_EYES,
+                section: DeckType::Main,
+                up: true
+            }
+        ));
+        let same = Box::new(current.clone());
+        assert!(!history.apply(&mut current, DeckEdit::Import { deck: same }));
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn repeated_edits_group_inside_the_window() {
+        let mut history = DeckHistory::new();
+        let mut current = deck(&[], &[], &[]);
+        for _ in 0..3 {
+            history.apply(
+                &mut current,
+                DeckEdit::Add {
+                    card: BLUE_EYES,
+                    section: DeckType::Main,
+                },
+            );
+        }
+        assert_eq!(history.applied().len(), 1);
+        assert_eq!(history.applied()[0].times, 3);
+        history.undo(&mut current);
+        assert!(current.main_deck.is_empty());
+    }
+
+    #[test]
+    fn repeated_edits_do_not_group_outside_the_window() {
+        let mut history = DeckHistory::new();
+        let mut current = deck(&[], &[], &[]);
+        history.apply(
+            &mut current,
+            DeckEdit::Add {
+                card: BLUE_EYES,
+                section: DeckType::Main,
+            },
+        );
+        age_last(&mut history);
+        history.apply(
+            &mut current,
+            DeckEdit::Add {
+                card: BLUE_EYES,
+                section: DeckType::Main,
+            },
+        );
+        assert_eq!(history.applied().len(), 2);
+        history.undo(&mut current);
+        assert_eq!(current.main_deck, vec![BLUE_EYES]);
+    }
+
+    #[test]
+    fn different_and_redone_edits_do_not_group() {
+        let mut history = DeckHistory::new();
+        let mut current = deck(&[], &[], &[]);
+        history.apply(
+            &mut current,
+            DeckEdit::Add {
+                card: BLUE_EYES,
+                section: DeckType::Main,
+            },
+        );
+        history.apply(
+            &mut current,
+            DeckEdit::Add {
+                card: DARK_MAGICIAN,
+                section: DeckType::Main,
+            },
+        );
+        assert_eq!(history.applied().len(), 2);
+        history.undo(&mut current);
+        history.redo(&mut current);
+        history.apply(
+            &mut current,
+            DeckEdit::Add {
+                card: DARK_MAGICIAN,
+                section: DeckType::Main,
+            },
+        );
+        assert_eq!(history.applied().len(), 3);
+    }
+
+    #[test]
+    fn new_edit_clears_redo() {
+        let mut history = DeckHistory::new();
+        let mut current = deck(&[], &[], &[]);
+        history.apply(
+            &mut current,
+            DeckEdit::Add {
+                card: BLUE_EYES,
+                section: DeckType::Main,
+            },
+        );
+        history.undo(&mut current);
+        assert!(history.can_redo());
+        history.apply(
+            &mut current,
+            DeckEdit::Add {
+                card: DARK_MAGICIAN,
+                section: DeckType::Main,
+            },
+        );
+        assert!(!history.can_redo());
+        assert!(!history.redo(&mut current));
+        assert_eq!(current.main_deck, vec![DARK_MAGICIAN]);
+    }
+
+    #[test]
+    fn jump_undoes_and_redoes_to_an_entry() {
+        let mut history = DeckHistory::new();
+        let mut current = deck(&[], &[], &[]);
+        for card in [BLUE_EYES, DARK_MAGICIAN, POT_OF_GREED] {
+            history.apply(&mut current, DeckEdit::Add { card, section: DeckType::Main });
+        }
+        history.jump(&mut current, 1);
+        assert_eq!(current.main_deck, vec![BLUE_EYES]);
+        assert_eq!((history.applied().len(), history.undone().len()), (1, 2));
+        history.jump(&mut current, 3);
+        assert_eq!(current.main_deck, vec![BLUE_EYES, DARK_MAGICIAN, POT_OF_GREED]);
+        history.jump(&mut current, 0);
+        assert!(current.main_deck.is_empty());
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn oldest_entries_are_dropped_past_the_limit() {
+        let mut history = DeckHistory::new();
+        let mut current = deck(&[], &[], &[]);
+        // alternating cards so no two edits in a row group
+        for i in 0..HISTORY_LIMIT + 5 {
+            let card = if i % 2 == 0 { BLUE_EYES } else { DARK_MAGICIAN };
+            history.apply(&mut current, DeckEdit::Add { card, section: DeckType::Main });
+        }
+        assert_eq!(history.applied().len(), HISTORY_LIMIT);
+        while history.undo(&mut current) {}
+        // the five edits that were forgotten can not be undone anymore
+        assert_eq!(current.main_deck, vec![BLUE_EYES, DARK_MAGICIAN, BLUE_EYES, DARK_MAGICIAN, BLUE_EYES]);
+    }
+}
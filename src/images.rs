@@ -0,0 +1,296 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use anyhow::anyhow;
+use eframe::{egui, epaint::TextureId};
+use egui_extras::RetainedImage;
+use instant::Instant;
+use poll_promise::Promise;
+use ygodeckbuilderproelite::{
+    progress::{Progress, ProgressUnit},
+    CardImage, ImageSize, NetworkPolicy, YugiohImage,
+};
+
+use crate::{downloads::Downloader, sizedbuffer::Buffer};
+
+pub const DEFAULT_TEXTURE_BUDGET: usize = 512 * 1024 * 1024;
+const MAX_DOWNLOADS: usize = 250;
+// failed images are retried on their own this many times, after that only a manual retry loads them again
+const MAX_ATTEMPTS: u32 = 5;
+const FIRST_RETRY: Duration = Duration::from_secs(2);
+// promise slots prefetching leaves free so images that are actually on screen never have to wait for one
+const PREFETCH_RESERVE: usize = 50;
+
+pub type ImageKey = (u32, ImageSize);
+type ImagePromise = Promise<Result<RetainedImage, anyhow::Error>>;
+
+// the error a promise resolves to when its image scrolled out of view before the downloader got to it
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Image request was cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+// the gui side of a YugiohImage: the texture once it is loaded, or the promise that is loading it
+#[derive(Debug, Clone, Default)]
+pub struct ImageSlot {
+    pub image: Option<TextureId>,
+    pub promise_index: Option<usize>,
+    // the frame this image was last asked for, textures that have not been asked for in a while are evicted first
+    last_used: u64,
+    // the frame this image was last prefetched in, prefetching does not count as drawing it
+    prefetched: u64,
+    failures: u32,
+    retry_at: Option<Instant>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageState {
+    Ready,
+    Loading,
+    Failed,
+}
+
+struct CachedTexture {
+    // keeping the RetainedImage alive keeps the texture alive, dropping it frees the texture
+    _image: RetainedImage,
+    bytes: usize,
+}
+
+// every card image the gui shows goes through here: the textures in memory, the requests loading them and their progress
+pub struct CardImages {
+    slots: HashMap<ImageKey, ImageSlot>,
+    textures: HashMap<ImageKey, CachedTexture>,
+    frame: u64,
+    used_bytes: usize,
+    pub budget_bytes: usize,
+    pub downloads: Downloader,
+    promises: Buffer<ImagePromise>,
+    pub progress: Progress,
+}
+
+impl CardImages {
+    pub fn new(budget_bytes: usize, downloads: Downloader) -> Self {
+        Self {
+            slots: HashMap::new(),
+            textures: HashMap::new(),
+            frame: 0,
+            used_bytes: 0,
+            budget_bytes,
+            downloads,
+            promises: Buffer::new(MAX_DOWNLOADS),
+            progress: Progress::new("Loading images", ProgressUnit::Items),
+        }
+    }
+
+    // every image that is drawn goes through here, which is what marks it as recently used
+    fn slot(&mut self, image: &YugiohImage) -> &mut ImageSlot {
+        let slot = self.slots.entry((image.id, image.size)).or_default();
+        slot.last_used = self.frame;
+        slot
+    }
+
+    pub fn used_bytes(&self) -> usize {
+        self.used_bytes
+    }
+
+    pub fn texture_count(&self) -> usize {
+        self.textures.len()
+    }
+
+    // draws one of a card's images into rect, requesting it if it is not loaded yet
+    pub fn paint(&mut self, painter: &egui::Painter, rect: egui::Rect, card_image: &CardImage, size: ImageSize, policy: NetworkPolicy) -> ImageState {
+        let image = card_image.get(size);
+        let frame = self.frame;
+        let slot = self.slots.entry((image.id, image.size)).or_default();
+        slot.last_used = frame;
+        if let Some(texture) = slot.image {
+            let mut mesh = egui::Mesh::with_texture(texture);
+            mesh.add_rect_with_uv(rect, egui::Rect::from_min_max(egui::Pos2::ZERO, egui::Pos2::new(1., 1.)), egui::Color32::WHITE);
+            painter.add(egui::Shape::Mesh(mesh));
+            return ImageState::Ready;
+        }
+        if slot.is_failed() {
+            // clicking the tile retries right away instead of waiting for the backoff, see retry
+            painter.text(
+                rect.center(),
+                egui::Align2::CENTER_CENTER,
+                "Failed\nclick to retry",
+                egui::FontId::default(),
+                egui::Color32::from_rgb(200, 80, 80),
+            );
+            if let Some(retry_in) = slot.retry_in() {
+                painter.ctx().request_repaint_after(retry_in);
+            }
+            return ImageState::Failed;
+        }
+        if slot.promise_index.is_none() && self.promises.free() > 0 {
+            if let Ok(index) = self.promises.try_add(self.downloads.request(card_image, size, policy)) {
+                slot.promise_index = Some(index);
+                self.progress.add_total(1);
+            }
+        }
+        // too small for the text, the tile background is placeholder enough
+        if rect.width() > 64. {
+            painter.text(rect.center(), egui::Align2::CENTER_CENTER, "Loading...", egui::FontId::default(), egui::Color32::from_rgb(0, 0, 0));
+        }
+        painter.ctx().request_repaint();
+        ImageState::Loading
+    }
+
+    // loads a failed image again without waiting for its next automatic attempt
+    pub fn retry(&mut self, image: &YugiohImage) {
+        self.slot(image).retry();
+    }
+
+    // queues an image that is about to scroll into view
+    pub fn prefetch(&mut self, card_image: &CardImage, size: ImageSize, policy: NetworkPolicy) {
+        let image = card_image.get(size);
+        let slot = self.slots.entry((image.id, image.size)).or_default();
+        slot.prefetched = self.frame;
+        if slot.image.is_some() || slot.promise_index.is_some() || slot.is_failed() || self.promises.free() <= PREFETCH_RESERVE {
+            return;
+        }
+        if let Ok(index) = self.promises.try_add(self.downloads.prefetch(card_image, size, policy)) {
+            slot.promise_index = Some(index);
+            self.progress.add_total(1);
+        }
+    }
+
+    // images that are still loading and were drawn or prefetched this frame, the downloader drops everything else from its queue
+    fn pending(&self, prefetched: bool) -> HashSet<ImageKey> {
+        self.slots
+            .iter()
+            .filter(|(_, slot)| slot.promise_index.is_some() && if prefetched { slot.prefetched } else { slot.last_used } == self.frame)
+            .map(|(key, _)| *key)
+            .collect()
+    }
+
+    // call once at the start of every frame, before anything is drawn
+    pub fn begin_frame(&mut self) {
+        let last_frame = self.frame;
+        self.frame += 1;
+        if self.used_bytes <= self.budget_bytes {
+            return;
+        }
+        // anything drawn last frame is probably still on screen, so it is never evicted even if that means going over budget
+        let mut candidates: Vec<(u64, ImageKey)> = self
+            .textures
+            .keys()
+            .map(|key| (self.slots.get(key).map(|slot| slot.last_used).unwrap_or(0), *key))
+            .filter(|(last_used, _)| *last_used < last_frame)
+            .collect();
+        candidates.sort_unstable_by_key(|(last_used, _)| *last_used);
+        for (_, key) in candidates {
+            if self.used_bytes <= self.budget_bytes {
+                break;
+            }
+            if let Some(texture) = self.textures.remove(&key) {
+                self.used_bytes -= texture.bytes;
+            }
+            // the image is reloaded from the disk cache the next time it is drawn
+            if let Some(slot) = self.slots.get_mut(&key) {
+                slot.image = None;
+            }
+        }
+    }
+
+    // call once at the end of every frame after everything is drawn, returns why any images failed to load
+    pub fn end_frame(&mut self, ctx: &egui::Context) -> Vec<anyhow::Error> {
+        self.downloads.retain(&self.pending(false), &self.pending(true));
+        let mut errors = Vec::new();
+        for (key, slot) in self.slots.iter_mut().filter(|(_, slot)| slot.promise_index.is_some()) {
+            let (done, result) = slot.check_promise(ctx, &mut self.promises, false);
+            if done {
+                self.progress.advance(1);
+            }
+            match result {
+                Ok(Some(image)) => {
+                    let [width, height] = image.size();
+                    let bytes = width * height * 4;
+                    self.used_bytes += bytes;
+                    if let Some(old) = self.textures.insert(*key, CachedTexture { _image: image, bytes }) {
+                        self.used_bytes -= old.bytes;
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    slot.failed();
+                    errors.push(e);
+                }
+            }
+        }
+        // start counting from zero again once everything loaded so the next batch gets its own bar and eta
+        if self.progress.snapshot().is_finished() {
+            self.progress.start("Loading images", ProgressUnit::Items, None);
+        }
+        errors
+    }
+}
+
+impl ImageSlot {
+    // true while a failed image waits for its next attempt, and for good once it ran out of attempts
+    pub fn is_failed(&self) -> bool {
+        self.failures > 0 && self.retry_at.map(|at| Instant::now() < at).unwrap_or(true)
+    }
+
+    // when the next automatic attempt is due, so the ui knows when to repaint
+    pub fn retry_in(&self) -> Option<Duration> {
+        self.retry_at.map(|at| at.saturating_duration_since(Instant::now()))
+    }
+
+    pub fn retry(&mut self) {
+        self.failures = 0;
+        self.retry_at = None;
+    }
+
+    fn failed(&mut self) {
+        self.failures += 1;
+        // 2, 4, 8, 16 seconds between attempts
+        self.retry_at = (self.failures < MAX_ATTEMPTS).then(|| Instant::now() + FIRST_RETRY * 2u32.pow(self.failures - 1));
+    }
+
+    pub fn check_promise(&mut self, ctx: &eframe::egui::Context, promises: &mut Buffer<ImagePromise>, not_ready_is_err: bool) -> (bool, Result<Option<RetainedImage>, anyhow::Error>) {
+        if let Some(promise_index) = self.promise_index {
+            let im = promises.get_ref(promise_index);
+            if let Some(im) = im {
+                if im.poll().is_ready() {
+                    let im = promises.try_take(promise_index);
+                    if let Some(im) = im {
+                        let im = im.block_and_take();
+                        self.promise_index = None;
+                        if let Ok(im) = im {
+                            self.image = Some(im.texture_id(ctx));
+                            self.failures = 0;
+                            self.retry_at = None;
+                            (true, Ok(Some(im)))
+                        } else if im.as_ref().err().map(|e| e.is::<Cancelled>()).unwrap_or(false) {
+                            // scrolled out of view, it is requested again the next time it is drawn
+                            (true, Ok(None))
+                        } else {
+                            (true, Err(anyhow!("{:?}\n{:?}", im.err().unwrap(), "Image promise exists but was not fulfilled")))
+                        }
+                    } else {
+                        (true, Err(anyhow!("{:?}", "Image promise could not be taken")))
+                    }
+                } else if not_ready_is_err {
+                    (true, Err(anyhow!("{:?}", "Image promise is not ready")))
+                } else {
+                    (false, Ok(None))
+                }
+            } else {
+                self.promise_index = None;
+                (true, Err(anyhow!("{:?}", "Image promise does not exist")))
+            }
+        } else {
+            (false, Ok(None))
+        }
+    }
+}
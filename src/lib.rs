@@ -0,0 +1,32 @@
+// the card data model, deck files, search and validation, shared by the gui and any other tooling
+pub mod appdirs;
+pub mod bulk;
+pub mod cardcache;
+pub mod deck;
+pub mod diff;
+pub mod diskcache;
+pub mod hash;
+pub mod history;
+pub mod http;
+pub mod loader;
+pub mod metadata;
+pub mod network;
+pub mod progress;
+pub mod search;
+pub mod siding;
+pub mod source;
+pub mod stats;
+pub mod validation;
+pub mod yugioh;
+
+#[cfg(test)]
+mod testcards;
+
+pub use appdirs::AppDirs;
+pub use deck::{DeckType, YugiohDeck};
+pub use metadata::DeckMetadata;
+pub use network::NetworkPolicy;
+pub use search::{search, SortOrder, SortingMode, SortingType, YugiohCardSearchCriteria};
+pub use siding::SidePlan;
+pub use validation::{validate, DeckIssue, Format};
+pub use yugioh::{BanStatus, BanlistInfo, CardImage, CardPrice, CardSet, ImageSize, MonsterStats, PriceVendor, YugiohCard, YugiohImage};
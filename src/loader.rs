@@ -0,0 +1,240 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cardcache, diskcache,
+    network::{self, NetworkPolicy, CARD_DATA_MAX_AGE},
+    progress::{Progress, ProgressUnit},
+    source::{CardSource, DatabaseResponse, Validators},
+    yugioh::{YugiohCard, YugiohCards},
+};
+
+#[derive(Debug, Clone)]
+pub enum LoadError {
+    // the request could not be sent or the body could not be read
+    Network(String),
+    // the api answered, but not with a success status
+    Status(u16),
+    // the data is not a valid card database
+    Parse(String),
+    // nothing is cached and the network policy does not allow downloading
+    Offline,
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::Network(e) => write!(f, "Could not reach the card database: {}", e),
+            LoadError::Status(status) => write!(f, "The card database answered with status {}", status),
+            LoadError::Parse(e) => write!(f, "The card database could not be read: {}", e),
+            LoadError::Offline => write!(f, "The card database is not cached and the network is disabled"),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+pub struct LoadedCards {
+    pub cards: Vec<YugiohCard>,
+    // set when something went wrong that did not stop the cards from loading
+    pub warning: Option<String>,
+}
+
+// kept next to the card data in cards.json.meta, what the server said about the cached copy and when we last asked
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CardDataMeta {
+    pub validators: Validators,
+    // seconds since the unix epoch
+    pub last_checked: Option<u64>,
+    pub last_changed: Option<u64>,
+}
+
+impl CardDataMeta {
+    fn path(cache: &Path) -> PathBuf {
+        cache.join("cards.json.meta")
+    }
+
+    // missing or unreadable metadata is the same as none, it only costs one full download
+    pub fn read(cache: &Path) -> Self {
+        std::fs::read_to_string(Self::path(cache)).ok().and_then(|text| serde_json::from_str(&text).ok()).unwrap_or_default()
+    }
+
+    fn write(&self, cache: &Path) {
+        let written = serde_json::to_vec_pretty(self)
+            .map_err(anyhow::Error::from)
+            .and_then(|bytes| Ok(diskcache::replace(&Self::path(cache), &bytes)?));
+        if let Err(e) = written {
+            eprintln!("Failed to write card data metadata: {}", e);
+        }
+    }
+
+    pub fn checked_age(&self) -> Option<Duration> {
+        age(self.last_checked)
+    }
+
+    pub fn changed_age(&self) -> Option<Duration> {
+        age(self.last_changed)
+    }
+}
+
+fn age(secs: Option<u64>) -> Option<Duration> {
+    (UNIX_EPOCH + Duration::from_secs(secs?)).elapsed().ok()
+}
+
+fn now() -> Option<u64> {
+    SystemTime::now().duration_since(UNIX_EPOCH).ok().map(|since| since.as_secs())
+}
+
+pub fn load_cards(cache: PathBuf, policy: NetworkPolicy, source: Arc<dyn CardSource>, progress: Progress) -> Result<LoadedCards, LoadError> {
+    let cache_path = cache.join("cards.json");
+    let bin_path = cache.join("cards.bin");
+    // data the server confirmed unchanged counts as fresh, even though the file itself was written long ago
+    let age = CardDataMeta::read(&cache).checked_age().or_else(|| network::cached_age(&cache_path));
+    let use_cached = policy.use_cached_age(age, CARD_DATA_MAX_AGE);
+    if use_cached && bin_path.exists() {
+        match cardcache::read(&bin_path, &cache_path, &progress) {
+            Ok(cards) => return Ok(LoadedCards { cards, warning: None }),
+            Err(e) => eprintln!("{}, falling back to the json card data", e),
+        }
+    }
+    let (loaded, parsed) = load_json(&cache, policy, use_cached, source.as_ref(), &progress)?;
+    // cards that came out of the binary cache are already in it
+    if parsed {
+        if let Err(e) = cardcache::write(&bin_path, &loaded.cards) {
+            eprintln!("Failed to write binary card cache: {}", e);
+        }
+    }
+    Ok(loaded)
+}
+
+// the cards and whether they were parsed from json, as opposed to read from the binary cache
+fn load_json(cache: &Path, policy: NetworkPolicy, use_cached: bool, source: &dyn CardSource, progress: &Progress) -> Result<(LoadedCards, bool), LoadError> {
+    let cache_path = cache.join("cards.json");
+    if use_cached {
+        match read_cache(&cache_path, progress) {
+            Some(Ok(cards)) => return Ok((LoadedCards { cards, warning: None }, true)),
+            Some(Err(e)) => {
+                // a truncated or otherwise broken cache is useless, get rid of it and download a fresh copy
+                eprintln!("{}, deleting the cache and downloading it again", e);
+                if let Err(e) = std::fs::remove_file(&cache_path) {
+                    eprintln!("Failed to delete corrupted card data: {}", e);
+                }
+            }
+            None => (),
+        }
+    }
+    if !policy.allows_network() {
+        return Err(LoadError::Offline);
+    }
+    let mut meta = CardDataMeta::read(cache);
+    // the validators only mean something while we still have the data they describe
+    let validators = if cache_path.exists() { meta.validators.clone() } else { Validators::default() };
+    let result = match download(&cache_path, source, &validators, progress) {
+        Ok(Some((loaded, validators))) => {
+            meta.validators = validators;
+            meta.last_changed = now();
+            Ok((loaded, true))
+        }
+        Ok(None) => match cached_cards(cache, progress) {
+            Some((cards, parsed)) => Ok((LoadedCards { cards, warning: None }, parsed)),
+            // the server says our copy is current but it is unreadable after all, fetch the whole thing
+            None => download(&cache_path, source, &Validators::default(), progress).and_then(|downloaded| {
+                let (loaded, validators) = downloaded.ok_or(LoadError::Status(304))?;
+                meta.validators = validators;
+                meta.last_changed = now();
+                Ok((loaded, true))
+            }),
+        },
+        Err(e) => {
+            // when a refresh fails we would rather show stale data than nothing at all
+            if !use_cached {
+                if let Some(Ok(cards)) = read_cache(&cache_path, progress) {
+                    return Ok((
+                        LoadedCards {
+                            cards,
+                            warning: Some(format!("{}, using the cached card data instead", e)),
+                        },
+                        true,
+                    ));
+                }
+            }
+            return Err(e);
+        }
+    };
+    if result.is_ok() {
+        meta.last_checked = now();
+        meta.write(cache);
+    }
+    result
+}
+
+// the cached cards after a 304, from the binary cache if it is still good since that is much faster to read
+// true when they had to be parsed from the json
+fn cached_cards(cache: &Path, progress: &Progress) -> Option<(Vec<YugiohCard>, bool)> {
+    let cache_path = cache.join("cards.json");
+    match cardcache::read(&cache.join("cards.bin"), &cache_path, progress) {
+        Ok(cards) => Some((cards, false)),
+        Err(_) => Some((read_cache(&cache_path, progress)?.ok()?, true)),
+    }
+}
+
+fn read_cache(cache_path: &Path, progress: &Progress) -> Option<Result<Vec<YugiohCard>, LoadError>> {
+    progress.start("Reading cached card data", ProgressUnit::Bytes, None);
+    let data = std::fs::read_to_string(cache_path).ok()?;
+    progress.advance(data.len() as u64);
+    Some(parse_cards(&data, progress))
+}
+
+// None when the server says the cached copy is still current
+fn download(cache_path: &Path, source: &dyn CardSource, validators: &Validators, progress: &Progress) -> Result<Option<(LoadedCards, Validators)>, LoadError> {
+    let (raw, validators) = match source.fetch_database_if_modified(validators, progress)? {
+        DatabaseResponse::Modified { data, validators } => (data, validators),
+        DatabaseResponse::NotModified => return Ok(None),
+    };
+    let data = String::from_utf8(raw).map_err(|e| LoadError::Parse(e.to_string()))?;
+    let cards = parse_cards(&data, progress)?;
+    // only cache data that actually parsed, so a bad response can never poison the cache
+    let warning = diskcache::replace(cache_path, data.as_bytes()).err().map(|e| format!("Failed to write card data to the cache: {}", e));
+    Ok(Some((LoadedCards { cards, warning }, validators)))
+}
+
+// the browser can neither block on the network nor keep a disk cache, so it downloads the card data every time it starts
+// the browser's own http cache still spares it the full download when the data did not change
+#[cfg(target_arch = "wasm32")]
+pub async fn fetch_cards(url: String, policy: NetworkPolicy, progress: Progress) -> Result<LoadedCards, LoadError> {
+    if !policy.allows_network() {
+        return Err(LoadError::Offline);
+    }
+    let response = reqwest::get(url.as_str()).await.map_err(|e| LoadError::Network(e.to_string()))?;
+    if !response.status().is_success() {
+        return Err(LoadError::Status(response.status().as_u16()));
+    }
+    progress.start("Downloading card data", ProgressUnit::Bytes, response.content_length());
+    let data = response.text().await.map_err(|e| LoadError::Network(e.to_string()))?;
+    progress.advance(data.len() as u64);
+    let cards = parse_cards(&data, &progress)?;
+    Ok(LoadedCards { cards, warning: None })
+}
+
+pub fn parse_cards(data: &str, progress: &Progress) -> Result<Vec<YugiohCard>, LoadError> {
+    progress.start("Parsing card data", ProgressUnit::Items, None);
+    let cards: YugiohCards = serde_json::from_str(data).map_err(|e| LoadError::Parse(e.to_string()))?;
+    progress.start("Parsing card data", ProgressUnit::Items, Some(cards.data.len() as u64));
+    let mut parsed_cards = Vec::with_capacity(cards.data.len());
+    for card in cards.data {
+        if !card.card_images.is_empty() && !card.card_prices.is_empty() {
+            parsed_cards.push(YugiohCard::from_raw(card));
+        }
+        progress.advance(1);
+    }
+    if parsed_cards.is_empty() {
+        Err(LoadError::Parse("no cards found".to_owned()))
+    } else {
+        Ok(parsed_cards)
+    }
+}
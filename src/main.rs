@@ -2,39 +2,65 @@
 // suppress warnings about unused code
 #[allow(unused_imports)]
 #[allow(unused_variables)]
-use std::time::Instant;
+use std::{sync::Arc, time::Instant};
 
 use eframe::egui::{self, ScrollArea};
-use egui_extras::RetainedImage;
 use poll_promise::Promise;
+use ygodeckbuilderproelite::{
+    deck::count_cards,
+    diskcache::CacheLimits,
+    history::{DeckEdit, DeckHistory},
+    http::HttpConfig,
+    loader::{self, LoadError, LoadedCards},
+    metadata,
+    progress::{format_bytes, Progress, ProgressUnit},
+    search, stats, validate, DeckType, Format, ImageSize, NetworkPolicy, PriceVendor, SortOrder, SortingMode, SortingType, YugiohCard, YugiohCardSearchCriteria, YugiohDeck,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use ygodeckbuilderproelite::{
+    diskcache::{self, CacheUsage, PruneReport},
+    source::{self, CardSource},
+    AppDirs,
+};
 // mod macros;
+mod compare;
+mod deckinfo;
+// the browser can not spawn threads or write files, it gets a downloader built on fetch instead
+#[cfg_attr(target_arch = "wasm32", path = "webdownloads.rs")]
+mod downloads;
+mod images;
+#[cfg(not(target_arch = "wasm32"))]
+mod offline;
+mod settings;
+mod sideplans;
 mod sizedbuffer;
-mod yugioh;
-use sizedbuffer::Buffer;
-use yugioh::{DeckType, YugiohCard, YugiohCardSearchCriteria, YugiohCards, YugiohDeck};
+mod store;
+mod tile;
+mod versions;
+use downloads::Downloader;
+use images::CardImages;
+use settings::{ListDisplayMode, Settings};
+use store::Store;
+use tile::{CardRow, CardTile, ASPECT_RATIO};
 const CARD_HEIGHT: f32 = 128.0;
-const ASPECT_RATIO: f32 = 2.25 / 3.25;
 const CARD_WIDTH: f32 = CARD_HEIGHT * ASPECT_RATIO;
 const CARD_MARGIN: f32 = 1.0;
-const CARD_ROUNDING: f32 = 1.0;
-const MAX_DOWNLOADS: usize = 250;
+const COMPACT_HEIGHT: f32 = 32.0;
+const DETAIL_IMAGE_WIDTH: f32 = 210.0;
+const DECK_FILE: &str = "deck.ydk";
+
+// the rows just outside the visible range, below first since that is the usual scroll direction, then a page further down
+fn prefetch_rows(visible: std::ops::Range<usize>, margin: usize, rows: usize) -> Vec<usize> {
+    let below = visible.end..(visible.end + margin + visible.len()).min(rows);
+    let above = visible.start.saturating_sub(margin)..visible.start;
+    below.chain(above.rev()).collect()
+}
 
 fn main() {
     #[cfg(not(target_arch = "wasm32"))]
     {
-        // first we make sure the appdata directory for the program exists
-        let appdata = dirs::data_local_dir().unwrap();
-        let appdata = appdata.join("yugiohdeckbuilder");
-        std::fs::create_dir_all(&appdata).unwrap();
-        // then we make sure the cache directory for the program exists
-        let cache = appdata.join("cache");
-        std::fs::create_dir_all(&cache).unwrap();
-        // then we make sure there is a small and large folder in the cache directory
-        let small = cache.join("small");
-        std::fs::create_dir_all(&small).unwrap();
-        let large = cache.join("large");
-        std::fs::create_dir_all(&large).unwrap();
-        let app_dirs = AppDirs { appdata, cache, small, large };
+        // first we make sure the appdata directory for the program and everything under it exists
+        let app_dirs = AppDirs::create(AppDirs::default_location().unwrap()).unwrap();
         let native_options = eframe::NativeOptions::default();
         eframe::run_native("Yugioh Deck Builder", native_options, Box::new(|cc| Box::new(App::new(cc, app_dirs))));
     }
@@ -50,125 +76,936 @@ fn main() {
 }
 #[allow(dead_code)]
 pub struct App {
-    p: Option<Promise<Vec<YugiohCard>>>,
+    p: Option<Promise<Result<LoadedCards, LoadError>>>,
+    load_error: Option<LoadError>,
+    load_warning: Option<String>,
+    load_progress: Progress,
     cards: Vec<YugiohCard>,
     deck: YugiohDeck,
+    deck_path: std::path::PathBuf,
+    // edits since the deck was last loaded or saved
+    deck_dirty: bool,
+    history: DeckHistory,
+    show_history: bool,
+    import_error: Option<String>,
+    // the saved versions of the open deck, newest first
+    versions: Vec<versions::Version>,
+    compare: compare::DeckCompare,
+    show_compare: bool,
+    side_plans: sideplans::SidePlans,
+    show_side_plans: bool,
+    deck_info: deckinfo::DeckInfo,
+    show_deck_info: bool,
+    deck_error: Option<String>,
+    // the card whose detail window is open
+    detail_card: Option<usize>,
+    // the card under the pointer and the rect of its tile, its large image is drawn next to it at the end of the frame
+    preview_card: Option<(usize, egui::Rect)>,
     list_display_mode: ListDisplayMode,
-    api_override: bool,
+    network_policy: NetworkPolicy,
     search_criteria: YugiohCardSearchCriteria,
     last_search_criteria: YugiohCardSearchCriteria,
-    search_results: Option<Vec<(usize, YugiohCard)>>,
-    cached_images: Vec<RetainedImage>,
-    image_promises: Buffer<Promise<Result<RetainedImage, anyhow::Error>>>,
+    search_results: Option<Vec<usize>>,
+    images: CardImages,
+    // rows loaded ahead above and below the visible ones, on top of the next page
+    prefetch_rows: usize,
+    #[cfg(not(target_arch = "wasm32"))]
+    offline: offline::OfflineDownload,
+    format: Format,
+    price_vendor: PriceVendor,
+    currency: String,
+    // the network settings the source was created with are only picked up again on the next start
+    http: HttpConfig,
+    // what was last written to the settings file, it is written again whenever the current settings differ
+    saved_settings: Settings,
     buffers: Vec<String>,
     sorting: SortingMode,
     last_sorting: SortingMode,
     request_repaint: bool,
     ppp: f32,
+    // the settings file and decks
+    store: Store,
+    #[cfg(not(target_arch = "wasm32"))]
     app_dirs: AppDirs,
+    #[cfg(not(target_arch = "wasm32"))]
+    source: Arc<dyn CardSource>,
+    show_settings: bool,
+    cache_limits: CacheLimits,
+    #[cfg(not(target_arch = "wasm32"))]
+    cache_usage: Option<CacheUsage>,
+    #[cfg(not(target_arch = "wasm32"))]
+    cache_task: Option<Promise<(Option<PruneReport>, CacheUsage)>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    cache_message: Option<String>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct AppDirs {
-    pub appdata: std::path::PathBuf,
-    pub cache: std::path::PathBuf,
-    pub small: std::path::PathBuf,
-    pub large: std::path::PathBuf,
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum ListDisplayMode {
-    Card,
-    ImageOnly,
-}
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-struct SortingMode {
-    stype: SortingType,
-    order: Ord,
-}
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum SortingType {
-    Name,
-    Id,
-}
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Ord {
-    Asc,
-    Dsc,
+#[cfg(not(target_arch = "wasm32"))]
+enum CacheTask {
+    Refresh,
+    Clear(std::path::PathBuf),
+    Prune,
 }
 impl App {
-    pub fn new(_: &eframe::CreationContext<'_>, app_dirs: AppDirs) -> Self {
+    pub fn new(cc: &eframe::CreationContext<'_>, #[cfg(not(target_arch = "wasm32"))] app_dirs: AppDirs) -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        let store = Store::new(app_dirs.appdata.clone());
+        #[cfg(target_arch = "wasm32")]
+        let store = Store::new();
+        let settings = Settings::load(&store, cc.storage);
+        // a broken proxy or certificate setting should not keep the app from starting, it can be fixed in the settings
+        #[cfg(not(target_arch = "wasm32"))]
+        let (source, load_warning) = match source::from_env(&settings.http) {
+            Ok(source) => (source, None),
+            Err(e) => (
+                source::from_env(&HttpConfig::default()).expect("Failed to create the default card source"),
+                Some(format!("{:#}, using the default network settings until the next start", e)),
+            ),
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        let downloads = Downloader::new(source.clone(), app_dirs.clone());
+        #[cfg(target_arch = "wasm32")]
+        let (downloads, load_warning) = (Downloader::new(), None);
+        let images = CardImages::new(settings.texture_budget_mib * 1024 * 1024, downloads);
+        #[cfg(not(target_arch = "wasm32"))]
+        images.downloads.set_large_only(settings.large_only);
+        let mut buffers = vec![String::new(); 10];
+        buffers[0] = settings.search.clone();
+        let deck_path = settings.deck_path.clone().unwrap_or_else(|| store.path(DECK_FILE));
+        buffers[1] = deck_path.display().to_string();
+        buffers[2] = settings.http.proxy.clone().unwrap_or_default();
+        buffers[3] = settings.http.ca_certificates.iter().map(|path| path.display().to_string()).collect::<Vec<_>>().join("\n");
         App {
             p: None,
+            load_error: None,
+            load_warning,
+            load_progress: Progress::new("Loading card data", ProgressUnit::Bytes),
             cards: Vec::new(),
             deck: YugiohDeck::new(false),
-            list_display_mode: ListDisplayMode::ImageOnly,
-            api_override: false,
-            search_criteria: YugiohCardSearchCriteria::new(),
+            deck_path,
+            deck_dirty: false,
+            history: DeckHistory::new(),
+            show_history: false,
+            import_error: None,
+            versions: Vec::new(),
+            compare: compare::DeckCompare::new(),
+            show_compare: false,
+            side_plans: sideplans::SidePlans::new(),
+            show_side_plans: false,
+            deck_info: deckinfo::DeckInfo::new(),
+            show_deck_info: false,
+            deck_error: None,
+            detail_card: None,
+            preview_card: None,
+            list_display_mode: settings.list_display_mode,
+            network_policy: settings.network_policy,
+            search_criteria: YugiohCardSearchCriteria { string: settings.search.clone() },
             search_results: None,
             last_search_criteria: YugiohCardSearchCriteria::new(),
-            cached_images: Vec::new(),
-            image_promises: Buffer::new(MAX_DOWNLOADS),
-            buffers: vec![String::new(); 10],
-            sorting: SortingMode {
-                stype: SortingType::Name,
-                order: Ord::Dsc,
-            },
+            images,
+            prefetch_rows: settings.prefetch_rows,
+            #[cfg(not(target_arch = "wasm32"))]
+            offline: offline::OfflineDownload::new(),
+            format: settings.format,
+            price_vendor: settings.price_vendor,
+            currency: settings.currency.clone(),
+            buffers,
+            sorting: settings.sorting,
             last_sorting: SortingMode {
                 stype: SortingType::Name,
-                order: Ord::Dsc,
+                order: SortOrder::Asc,
             },
             request_repaint: false,
-            ppp: 1.0,
+            ppp: settings.ppp,
+            store,
+            #[cfg(not(target_arch = "wasm32"))]
             app_dirs,
+            #[cfg(not(target_arch = "wasm32"))]
+            source,
+            show_settings: false,
+            cache_limits: settings.cache_limits,
+            #[cfg(not(target_arch = "wasm32"))]
+            cache_usage: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            cache_task: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            cache_message: None,
+            http: settings.http.clone(),
+            saved_settings: settings,
+        }
+    }
+
+    fn settings(&self) -> Settings {
+        let default_deck = self.store.path(DECK_FILE);
+        Settings {
+            ppp: self.ppp,
+            list_display_mode: self.list_display_mode,
+            sorting: self.sorting,
+            search: self.search_criteria.string.clone(),
+            network_policy: self.network_policy,
+            format: self.format,
+            price_vendor: self.price_vendor,
+            currency: self.currency.clone(),
+            texture_budget_mib: self.images.budget_bytes / (1024 * 1024),
+            prefetch_rows: self.prefetch_rows,
+            large_only: self.images.downloads.large_only(),
+            cache_limits: self.cache_limits,
+            deck_path: (self.deck_path != default_deck).then(|| self.deck_path.clone()),
+            http: self.http.clone(),
+        }
+    }
+
+    // writes the settings file whenever something changed, but not while a slider is still being dragged
+    fn save_settings(&mut self, ctx: &egui::Context) {
+        let settings = self.settings();
+        if settings == self.saved_settings || ctx.input().pointer.any_down() {
+            return;
+        }
+        if let Err(e) = settings.save(&self.store) {
+            eprintln!("Failed to save settings: {}", e);
+        }
+        self.saved_settings = settings;
+    }
+
+    fn open_deck(&mut self, path: std::path::PathBuf) {
+        // a missing or unreadable deck is just an empty one
+        self.deck = match self.store.read(&path) {
+            Some(text) => YugiohDeck::from_ydk(&text, &self.cards),
+            None => YugiohDeck::new(true),
+        };
+        self.deck_path = path;
+        self.deck_dirty = false;
+        self.history.clear();
+        self.versions = versions::list(&self.store, &self.deck_path);
+        self.side_plans = sideplans::SidePlans::new();
+        self.deck_info.reset(&self.deck.metadata);
+        // a deck built for a format is checked against it
+        if let Some(format) = self.deck.metadata.format {
+            self.format = format;
+        }
+        self.deck_error = None;
+    }
+
+    // disk cache work happens off the ui thread, the usage is recounted afterwards so the settings page stays accurate
+    #[cfg(not(target_arch = "wasm32"))]
+    fn spawn_cache_task(&mut self, task: CacheTask) {
+        if self.cache_task.is_some() {
+            return;
+        }
+        let app_dirs = self.app_dirs.clone();
+        let limits = self.cache_limits;
+        self.cache_task = Some(Promise::spawn_thread("cache", move || {
+            let report = match task {
+                CacheTask::Refresh => None,
+                CacheTask::Clear(dir) => Some(diskcache::clear(&dir)),
+                CacheTask::Prune => Some(diskcache::prune(&app_dirs, limits)),
+            };
+            (report, diskcache::usage(&app_dirs))
+        }));
+    }
+
+    fn settings_window(&mut self, ctx: &egui::Context) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(task) = &self.cache_task {
+            if task.ready().is_some() {
+                let (report, usage) = self.cache_task.take().unwrap().block_and_take();
+                if let Some(report) = report {
+                    self.cache_message = Some(format!("Removed {} images, {}", report.files, format_bytes(report.bytes)));
+                }
+                self.cache_usage = Some(usage);
+            } else {
+                self.request_repaint = true;
+            }
+        }
+        if !self.show_settings {
+            return;
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.cache_usage.is_none() {
+            self.spawn_cache_task(CacheTask::Refresh);
+        }
+        let mut open = self.show_settings;
+        #[cfg(not(target_arch = "wasm32"))]
+        let mut task = None;
+        let mut open_deck = None;
+        let mut reload = false;
+        egui::Window::new("Settings").open(&mut open).show(ctx, |ui| {
+            ui.heading("General");
+            egui::Grid::new("general settings").num_columns(2).show(ui, |ui| {
+                ui.label("Format");
+                egui::ComboBox::from_id_source("format").selected_text(self.format.name()).show_ui(ui, |ui| {
+                    for format in Format::ALL {
+                        ui.selectable_value(&mut self.format, format, format.name());
+                    }
+                });
+                ui.end_row();
+                ui.label("Prices from");
+                egui::ComboBox::from_id_source("price vendor").selected_text(self.price_vendor.name()).show_ui(ui, |ui| {
+                    for vendor in PriceVendor::ALL {
+                        ui.selectable_value(&mut self.price_vendor, vendor, vendor.name());
+                    }
+                });
+                ui.end_row();
+                ui.label("Network");
+                egui::ComboBox::from_id_source("network policy").selected_text(self.network_policy.name()).show_ui(ui, |ui| {
+                    for policy in NetworkPolicy::ALL {
+                        ui.selectable_value(&mut self.network_policy, policy, policy.name());
+                    }
+                });
+                ui.end_row();
+                ui.label("Currency symbol");
+                ui.add(egui::TextEdit::singleline(&mut self.currency).desired_width(40.));
+                ui.end_row();
+                ui.label("Deck file");
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut self.buffers[1]);
+                    if ui.add_enabled(!self.cards.is_empty(), egui::Button::new("Open")).clicked() {
+                        open_deck = Some(std::path::PathBuf::from(self.buffers[1].trim()));
+                    }
+                });
+                ui.end_row();
+            });
+            if self.deck_dirty {
+                ui.label("Opening another deck discards unsaved changes");
+            }
+            // images follow the network policy right away, the card data only when it is loaded again
+            if ui.add_enabled(!self.cards.is_empty() && !self.deck_dirty, egui::Button::new("Reload card data")).clicked() {
+                reload = true;
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            self.network_settings(ui);
+            ui.separator();
+            ui.heading("Memory");
+            ui.horizontal(|ui| {
+                let mut budget_mib = self.images.budget_bytes / (1024 * 1024);
+                if ui.add(egui::Slider::new(&mut budget_mib, 64..=4096).text("texture memory (MiB)")).changed() {
+                    self.images.budget_bytes = budget_mib * 1024 * 1024;
+                }
+            });
+            ui.add(egui::Slider::new(&mut self.prefetch_rows, 0..=20).text("rows to load ahead"));
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                let mut large_only = self.images.downloads.large_only();
+                if ui.checkbox(&mut large_only, "Download large images only, small ones are made from them").changed() {
+                    self.images.downloads.set_large_only(large_only);
+                }
+            }
+            ui.label(format!(
+                "{} textures, {} in use, {} images queued",
+                self.images.texture_count(),
+                format_bytes(self.images.used_bytes() as u64),
+                self.images.downloads.queued()
+            ));
+            #[cfg(not(target_arch = "wasm32"))]
+            self.cache_settings(ui, &mut task);
+        });
+        self.show_settings = open;
+        if let Some(path) = open_deck {
+            self.open_deck(path);
+        }
+        if reload {
+            // the deck and every open window refer to cards by index, they are all set up again once the new data is in
+            self.cards.clear();
+            self.search_results = None;
+            self.detail_card = None;
+            self.preview_card = None;
+            // the last diff is kept by card index and only worked out again when the deck changes, which it may not
+            self.compare.reset(&self.versions);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(task) = task {
+            self.spawn_cache_task(task);
+        }
+    }
+
+    // the browser makes its requests itself, so these only exist natively
+    #[cfg(not(target_arch = "wasm32"))]
+    fn network_settings(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("Network");
+        ui.label("Changes here apply the next time the app starts");
+        egui::Grid::new("network settings").num_columns(2).show(ui, |ui| {
+            ui.label("Proxy");
+            if ui.add(egui::TextEdit::singleline(&mut self.buffers[2]).hint_text("http://proxy:8080")).changed() {
+                let proxy = self.buffers[2].trim();
+                self.http.proxy = (!proxy.is_empty()).then(|| proxy.to_owned());
+            }
+            ui.end_row();
+            ui.label("User agent");
+            ui.text_edit_singleline(&mut self.http.user_agent);
+            ui.end_row();
+            ui.label("Connect timeout");
+            ui.add(egui::DragValue::new(&mut self.http.connect_timeout_secs).clamp_range(1..=600).suffix(" s"));
+            ui.end_row();
+            ui.label("Request timeout");
+            ui.horizontal(|ui| {
+                let mut limited = self.http.timeout_secs.is_some();
+                if ui.checkbox(&mut limited, "").changed() {
+                    self.http.timeout_secs = limited.then(|| HttpConfig::default().timeout_secs.unwrap_or(300));
+                }
+                if let Some(secs) = self.http.timeout_secs.as_mut() {
+                    ui.add(egui::DragValue::new(secs).clamp_range(1..=3600).suffix(" s"));
+                }
+            });
+            ui.end_row();
+            ui.label("Root certificates");
+            if ui
+                .add(egui::TextEdit::multiline(&mut self.buffers[3]).desired_rows(2).hint_text("one pem or der file per line"))
+                .changed()
+            {
+                self.http.ca_certificates = self.buffers[3].lines().map(str::trim).filter(|line| !line.is_empty()).map(std::path::PathBuf::from).collect();
+            }
+            ui.end_row();
+        });
+    }
+
+    // there is no disk cache in the browser, it caches images itself
+    #[cfg(not(target_arch = "wasm32"))]
+    fn cache_settings(&mut self, ui: &mut egui::Ui, task: &mut Option<CacheTask>) {
+        ui.separator();
+        ui.heading("Disk cache");
+        if let Some(usage) = &self.cache_usage {
+            egui::Grid::new("cache_usage").striped(true).show(ui, |ui| {
+                for tier in &usage.tiers {
+                    ui.label(format!("{} images", tier.size));
+                    ui.label(format!("{} files", tier.files));
+                    ui.label(format_bytes(tier.bytes));
+                    if ui.button("Clear").clicked() {
+                        *task = Some(CacheTask::Clear(tier.dir.clone()));
+                    }
+                    ui.end_row();
+                }
+                ui.label("Total");
+                ui.label("");
+                ui.label(format_bytes(usage.image_bytes()));
+                ui.end_row();
+            });
+            match (usage.card_data_age, usage.card_data_checked) {
+                (None, _) => ui.label("Card data: not cached"),
+                (Some(age), None) => ui.label(format!("Card data: {}, downloaded {} ago", format_bytes(usage.card_data_bytes), diskcache::format_age(age))),
+                (Some(_), Some(checked)) => ui.label(format!(
+                    "Card data: {}, checked for updates {} ago, last changed {} ago",
+                    format_bytes(usage.card_data_bytes),
+                    diskcache::format_age(checked),
+                    usage.card_data_changed.map(diskcache::format_age).unwrap_or_else(|| "an unknown time".to_owned())
+                )),
+            };
+        } else {
+            ui.spinner();
+        }
+        ui.horizontal(|ui| {
+            let mut max_mib = self.cache_limits.max_bytes / (1024 * 1024);
+            if ui.add(egui::Slider::new(&mut max_mib, 16..=16384).logarithmic(true).text("max image cache (MiB)")).changed() {
+                self.cache_limits.max_bytes = max_mib * 1024 * 1024;
+            }
+        });
+        ui.horizontal(|ui| {
+            let mut limit_age = self.cache_limits.max_age_days.is_some();
+            if ui.checkbox(&mut limit_age, "Drop images unused for").changed() {
+                self.cache_limits.max_age_days = if limit_age { Some(90) } else { None };
+            }
+            if let Some(days) = self.cache_limits.max_age_days.as_mut() {
+                ui.add(egui::DragValue::new(days).clamp_range(1..=3650).suffix(" days"));
+            }
+        });
+        ui.horizontal(|ui| {
+            if ui.button("Prune now").clicked() {
+                *task = Some(CacheTask::Prune);
+            }
+            if ui.button("Refresh").clicked() {
+                *task = Some(CacheTask::Refresh);
+            }
+            if self.cache_task.is_some() {
+                ui.spinner();
+            }
+        });
+        if let Some(message) = &self.cache_message {
+            ui.label(message);
+        }
+        if !self.cards.is_empty() {
+            ui.separator();
+            ui.heading("Offline images");
+            self.offline.ui(ui, &self.cards, &self.deck, &self.app_dirs, &self.source);
+        }
+    }
+
+    // a list row for a card, hovering it previews the card and clicking a failed image retries it
+    fn card_row(&mut self, ui: &mut egui::Ui, card: usize, image_size: ImageSize, height: f32, count: Option<usize>) -> egui::Response {
+        let mut row = CardRow::new(&mut self.images, &self.cards[card], image_size, height).policy(self.network_policy);
+        row = match count {
+            Some(count) => row.count(count),
+            None => row.text_color(tile::deck_color(self.deck.contains_card(card))),
+        };
+        let response = ui.add(row);
+        if response.hovered() {
+            self.preview_card = Some((card, response.rect));
+        }
+        response
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load_cards(&self) -> Promise<Result<LoadedCards, LoadError>> {
+        let policy = self.network_policy;
+        let cache = self.app_dirs.cache.clone();
+        let source = self.source.clone();
+        let progress = self.load_progress.clone();
+        Promise::spawn_thread("data", move || loader::load_cards(cache, policy, source, progress))
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn load_cards(&self) -> Promise<Result<LoadedCards, LoadError>> {
+        Promise::spawn_local(loader::fetch_cards(
+            ygodeckbuilderproelite::source::YGOPRODECK_CARD_INFO_URL.to_owned(),
+            self.network_policy,
+            self.load_progress.clone(),
+        ))
+    }
+
+    fn add_to_deck(&mut self, card: usize) {
+        let section = if self.cards[card].is_extra_deck() { DeckType::Extra } else { DeckType::Main };
+        self.edit_deck(DeckEdit::Add { card, section });
+    }
+
+    // every change to the deck goes through here so it can be undone
+    fn edit_deck(&mut self, edit: DeckEdit) {
+        if self.history.apply(&mut self.deck, edit) {
+            self.deck_dirty = true;
+        }
+    }
+
+    fn undo(&mut self) {
+        if self.history.undo(&mut self.deck) {
+            self.deck_changed_by_history();
         }
     }
+
+    fn redo(&mut self) {
+        if self.history.redo(&mut self.deck) {
+            self.deck_changed_by_history();
+        }
+    }
+
+    // the tags field keeps its own text, it has to show the tags the history put back
+    fn deck_changed_by_history(&mut self) {
+        self.deck_dirty = true;
+        self.deck_info.reset(&self.deck.metadata);
+    }
+
+    // ctrl+z and ctrl+shift+z, or cmd on a mac, as long as no text field has the keyboard since those have their own undo
+    fn history_shortcuts(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        let (redo, undo) = {
+            let mut input = ctx.input_mut();
+            let redo = input.consume_key(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT, egui::Key::Z) || input.consume_key(egui::Modifiers::COMMAND, egui::Key::Y);
+            (redo, input.consume_key(egui::Modifiers::COMMAND, egui::Key::Z))
+        };
+        if redo {
+            self.redo();
+        } else if undo {
+            self.undo();
+        }
+    }
+
+    // every edit since the deck was opened, clicking one undoes or redoes everything up to it
+    fn history_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_history;
+        let mut jump = None;
+        egui::Window::new("History").open(&mut open).default_width(280.).show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if ui.add_enabled(self.history.can_undo(), egui::Button::new("Undo")).clicked() {
+                    jump = Some(self.history.applied().len() - 1);
+                }
+                if ui.add_enabled(self.history.can_redo(), egui::Button::new("Redo")).clicked() {
+                    jump = Some(self.history.applied().len() + 1);
+                }
+            });
+            ui.separator();
+            ScrollArea::vertical().show(ui, |ui| {
+                if ui.selectable_label(self.history.applied().is_empty(), "Opened the deck").clicked() {
+                    jump = Some(0);
+                }
+                let applied = self.history.applied().len();
+                for (i, entry) in self.history.applied().iter().enumerate() {
+                    if ui.selectable_label(i + 1 == applied, entry.describe(&self.cards)).clicked() {
+                        jump = Some(i + 1);
+                    }
+                }
+                // undone edits are greyed out, the next one to redo comes first
+                for (i, entry) in self.history.undone().iter().rev().enumerate() {
+                    let text = egui::RichText::new(entry.describe(&self.cards)).color(ui.visuals().weak_text_color());
+                    if ui.selectable_label(false, text).clicked() {
+                        jump = Some(applied + i + 1);
+                    }
+                }
+            });
+        });
+        self.show_history = open;
+        if let Some(applied) = jump {
+            if applied != self.history.applied().len() {
+                self.history.jump(&mut self.deck, applied);
+                self.deck_changed_by_history();
+            }
+        }
+    }
+
+    // every save also keeps a copy of the deck as a new version
+    fn save_deck(&mut self) {
+        // the times only move when the cards, plans or metadata did, so saving an unchanged deck writes the same text
+        // and does not leave another copy in the versions
+        let mut text = self.deck.to_ydk(&self.cards);
+        if self.store.read(&self.deck_path).as_deref() != Some(text.as_str()) {
+            self.deck.metadata.touch(store::unix_time());
+            text = self.deck.to_ydk(&self.cards);
+        }
+        match self.store.write(&self.deck_path, &text) {
+            Ok(()) => {
+                self.deck_dirty = false;
+                self.deck_error = versions::snapshot(&self.store, &self.deck_path, &text)
+                    .err()
+                    .map(|e| format!("Saved, but failed to keep a version: {}", e));
+                self.versions = versions::list(&self.store, &self.deck_path);
+            }
+            Err(e) => self.deck_error = Some(format!("Failed to save {}: {}", self.deck_path.display(), e)),
+        }
+    }
+
+    fn compare_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_compare;
+        egui::Window::new("Compare decks").open(&mut open).default_width(360.).show(ctx, |ui| {
+            self.compare.ui(ui, &self.cards, &self.deck, &self.store, &self.versions);
+        });
+        self.show_compare = open;
+    }
+
+    fn side_plans_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_side_plans;
+        // the window edits a copy, so the changes can go through the history
+        let mut edited = self.deck.clone();
+        let mut changed = false;
+        egui::Window::new("Side plans").open(&mut open).default_width(520.).show(ctx, |ui| {
+            changed = self.side_plans.ui(ui, &mut edited, &self.cards, self.format, &self.store, &self.deck_path);
+        });
+        self.show_side_plans = open;
+        if changed {
+            self.edit_deck(DeckEdit::Plans { plans: edited.side_plans });
+        }
+    }
+
+    fn deck_info_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_deck_info;
+        let mut edited = self.deck.clone();
+        let mut changed = false;
+        egui::Window::new("Deck info").open(&mut open).default_width(360.).show(ctx, |ui| {
+            changed = self.deck_info.ui(ui, &mut edited, &self.cards);
+        });
+        self.show_deck_info = open;
+        if changed {
+            self.edit_deck(DeckEdit::Info { metadata: Box::new(edited.metadata) });
+            if let Some(format) = self.deck.metadata.format {
+                self.format = format;
+            }
+        }
+    }
+
+    fn card_detail_window(&mut self, ctx: &egui::Context) {
+        let card = match self.detail_card {
+            Some(card) => card,
+            None => return,
+        };
+        let mut open = true;
+        // deck edits are applied after the window is drawn, the window borrows the card the whole time
+        let mut edit = None;
+        let mut role = None;
+        egui::Window::new(self.cards[card].name.clone())
+            .id(egui::Id::new("card detail"))
+            .open(&mut open)
+            .default_width(560.)
+            .show(ctx, |ui| {
+                ui.horizontal_top(|ui| {
+                    let size = egui::Vec2::new(DETAIL_IMAGE_WIDTH, DETAIL_IMAGE_WIDTH / ASPECT_RATIO);
+                    ui.add(CardTile::new(&mut self.images, &self.cards[card], ImageSize::Large, size).policy(self.network_policy));
+                    let details = &self.cards[card];
+                    ui.vertical(|ui| {
+                        egui::Grid::new("card detail stats").num_columns(2).show(ui, |ui| {
+                            let mut row = |label: &str, value: String| {
+                                ui.label(label);
+                                ui.label(value);
+                                ui.end_row();
+                            };
+                            row("Type", details.card_type.clone());
+                            row("Race", details.race.clone());
+                            if details.archetype != "None" {
+                                row("Archetype", details.archetype.clone());
+                            }
+                            let stats = &details.stats;
+                            if let Some(attribute) = &stats.attribute {
+                                row("Attribute", attribute.clone());
+                            }
+                            if let Some(level) = stats.level {
+                                row(if details.card_type.contains("XYZ") { "Rank" } else { "Level" }, level.to_string());
+                            }
+                            if let Some(link) = stats.link {
+                                row("Link", link.to_string());
+                            }
+                            if let Some(scale) = stats.scale {
+                                row("Scale", scale.to_string());
+                            }
+                            if let Some(atk) = stats.atk {
+                                let def = stats.def.map(|def| def.to_string()).unwrap_or_else(|| "-".to_owned());
+                                row("ATK / DEF", format!("{} / {}", atk, def));
+                            }
+                            row("TCG", details.banlist.tcg.name().to_owned());
+                            row("OCG", details.banlist.ocg.name().to_owned());
+                            row("GOAT", details.banlist.goat.name().to_owned());
+                        });
+                        ui.separator();
+                        // siding moves a copy between the side deck and whichever deck the card belongs in
+                        let home = if details.is_extra_deck() { DeckType::Extra } else { DeckType::Main };
+                        egui::Grid::new("card detail deck").num_columns(7).show(ui, |ui| {
+                            for section in [DeckType::Main, DeckType::Extra, DeckType::Side] {
+                                let count = self.deck.section(section).map(|cards| cards.iter().filter(|c| **c == card).count()).unwrap_or(0);
+                                ui.label(section.name());
+                                ui.label(count.to_string());
+                                if ui.add_enabled(count > 0, egui::Button::new("-")).clicked() {
+                                    edit = Some(DeckEdit::Remove { card, section });
+                                }
+                                if ui.button("+").clicked() {
+                                    edit = Some(DeckEdit::Add { card, section });
+                                }
+                                if ui.add_enabled(count > 0, egui::Button::new("Up")).on_hover_text("Move up in the deck list").clicked() {
+                                    edit = Some(DeckEdit::Reorder { card, section, up: true });
+                                }
+                                if ui.add_enabled(count > 0, egui::Button::new("Down")).on_hover_text("Move down in the deck list").clicked() {
+                                    edit = Some(DeckEdit::Reorder { card, section, up: false });
+                                }
+                                let to = if section == DeckType::Side { home } else { DeckType::Side };
+                                if ui.add_enabled(count > 0, egui::Button::new(format!("To {}", to.name().to_lowercase()))).clicked() {
+                                    edit = Some(DeckEdit::Move { card, from: section, to });
+                                }
+                                ui.end_row();
+                            }
+                        });
+                        // roles only mean something for cards the deck plays
+                        if self.deck.count(card) > 0 {
+                            ui.horizontal_wrapped(|ui| {
+                                ui.label("Roles");
+                                let roles = self.deck.metadata.roles(card);
+                                for name in metadata::CARD_ROLES {
+                                    if ui.selectable_label(roles.iter().any(|r| r.eq_ignore_ascii_case(name)), name).clicked() {
+                                        role = Some(name);
+                                    }
+                                }
+                            });
+                        }
+                    });
+                });
+                let details = &self.cards[card];
+                ui.separator();
+                ScrollArea::vertical().id_source("card detail text").max_height(160.).show(ui, |ui| {
+                    // a read only text edit, so the text can be selected and copied
+                    ui.add(egui::TextEdit::multiline(&mut details.desc.as_str()).desired_width(f32::INFINITY));
+                });
+                ui.collapsing("Prices", |ui| {
+                    egui::Grid::new("card detail prices").num_columns(2).striped(true).show(ui, |ui| {
+                        for vendor in PriceVendor::ALL {
+                            let price = format!("{}{:.2}", self.currency, details.card_prices.get(vendor));
+                            // the vendor picked in the settings stands out
+                            if vendor == self.price_vendor {
+                                ui.strong(vendor.name());
+                                ui.strong(price);
+                            } else {
+                                ui.label(vendor.name());
+                                ui.label(price);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+                let sets = details.card_sets.as_deref().unwrap_or_default();
+                ui.collapsing(format!("Printings ({})", sets.len()), |ui| {
+                    ScrollArea::vertical().id_source("card detail sets").max_height(200.).show(ui, |ui| {
+                        egui::Grid::new("card detail sets grid").num_columns(4).striped(true).show(ui, |ui| {
+                            for set in sets {
+                                ui.label(&set.set_name);
+                                ui.label(&set.set_code);
+                                ui.label(&set.set_rarity);
+                                ui.label(format!("{}{:.2}", self.currency, set.set_price));
+                                ui.end_row();
+                            }
+                        });
+                    });
+                });
+            });
+        if let Some(edit) = edit {
+            self.edit_deck(edit);
+        }
+        if let Some(role) = role {
+            let mut metadata = self.deck.metadata.clone();
+            metadata.toggle_role(card, role);
+            self.edit_deck(DeckEdit::Info { metadata: Box::new(metadata) });
+        }
+        if !open {
+            self.detail_card = None;
+        }
+    }
+
+    fn deck_panel(&mut self, ctx: &egui::Context) {
+        egui::SidePanel::right("deck").resizable(true).default_width(280.0).show(ctx, |ui| {
+            let title = if self.deck.metadata.name.is_empty() { "Deck".to_owned() } else { self.deck.metadata.name.clone() };
+            ui.horizontal(|ui| {
+                ui.heading(title);
+                if ui.add_enabled(self.deck_dirty, egui::Button::new("Save")).clicked() {
+                    self.save_deck();
+                }
+                if ui.add_enabled(self.history.can_undo(), egui::Button::new("Undo")).on_hover_text("Ctrl+Z").clicked() {
+                    self.undo();
+                }
+                if ui.add_enabled(self.history.can_redo(), egui::Button::new("Redo")).on_hover_text("Ctrl+Shift+Z").clicked() {
+                    self.redo();
+                }
+            });
+            ui.horizontal(|ui| {
+                if ui.selectable_label(self.show_deck_info, "Info").clicked() {
+                    self.show_deck_info = !self.show_deck_info;
+                }
+                if ui.selectable_label(self.show_history, "History").clicked() {
+                    self.show_history = !self.show_history;
+                }
+                if ui.selectable_label(self.show_compare, format!("Compare ({} versions)", self.versions.len())).clicked() {
+                    self.show_compare = !self.show_compare;
+                    self.compare.reset(&self.versions);
+                }
+                if ui.selectable_label(self.show_side_plans, format!("Side plans ({})", self.deck.side_plans.len())).clicked() {
+                    self.show_side_plans = !self.show_side_plans;
+                }
+            });
+            if self.deck_dirty {
+                ui.label("unsaved changes");
+            }
+            if let Some(error) = &self.deck_error {
+                ui.colored_label(egui::Color32::RED, error);
+            }
+            let price = stats::deck_stats(&self.deck, &self.cards, self.price_vendor).price;
+            ui.label(format!("{}{:.2} on {}", self.currency, price, self.price_vendor.name()));
+            match validate(&self.deck, &self.cards, self.format).len() {
+                0 => ui.label(format!("Legal in {}", self.format.name())),
+                issues => ui.colored_label(egui::Color32::YELLOW, format!("{} problems in {}", issues, self.format.name())),
+            };
+            ui.collapsing("Import", |ui| {
+                ui.add(
+                    egui::TextEdit::multiline(&mut self.buffers[4])
+                        .desired_rows(4)
+                        .hint_text("a ydk file, a ydke:// url or one \"3 Card Name\" per line"),
+                );
+                ui.horizontal(|ui| {
+                    // replaces the whole deck, undo brings the old one back
+                    if ui.button("Replace deck").clicked() {
+                        match YugiohDeck::parse_any(&self.buffers[4], &self.cards) {
+                            Ok(deck) => {
+                                self.edit_deck(DeckEdit::Import { deck: Box::new(deck) });
+                                self.deck_info.reset(&self.deck.metadata);
+                                self.buffers[4].clear();
+                                self.import_error = None;
+                            }
+                            Err(e) => self.import_error = Some(e.to_string()),
+                        }
+                    }
+                    if let Some(error) = &self.import_error {
+                        ui.colored_label(egui::Color32::RED, error);
+                    }
+                });
+            });
+            ui.label("Click a card for details, right click to remove a copy");
+            ui.separator();
+            ScrollArea::vertical().show(ui, |ui| {
+                for (name, deck_type) in [("Main", DeckType::Main), ("Extra", DeckType::Extra), ("Side", DeckType::Side)] {
+                    let counts = self.deck.section(deck_type).map(|section| count_cards(section)).unwrap_or_default();
+                    ui.strong(format!("{} ({})", name, counts.iter().map(|(_, count)| count).sum::<usize>()));
+                    for (card, count) in counts {
+                        let response = self.card_row(ui, card, ImageSize::Cropped, COMPACT_HEIGHT, Some(count));
+                        if response.clicked() {
+                            self.detail_card = Some(card);
+                        } else if response.secondary_clicked() {
+                            self.edit_deck(DeckEdit::Remove { card, section: deck_type });
+                        }
+                    }
+                    ui.separator();
+                }
+            });
+        });
+    }
 }
 impl eframe::App for App {
+    // only called where eframe has storage, which is always the case in the browser
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        self.settings().store(storage);
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         // println!("update");
+        self.images.begin_frame();
+        if !self.cards.is_empty() {
+            self.deck_panel(ctx);
+        }
         egui::CentralPanel::default().show(ctx, |ui| {
             if self.cards.is_empty() {
-                if self.p.is_none() {
-                    let api_override = self.api_override;
-                    let app_dirs = self.app_dirs.clone();
-                    self.p = Some(Promise::spawn_thread("data", move || {
-                        let data: Result<String, anyhow::Error> = if api_override {
-                            Err(anyhow::Error::msg("API override"))
-                        } else {
-                            let r = std::fs::read_to_string(app_dirs.cache.join("cards.json"));
-                            if let Ok(data) = r {
-                                Ok(data)
-                            } else {
-                                Err(anyhow::Error::msg("Failed to read data.json"))
-                            }
-                        };
-                        let cards: YugiohCards;
-                        if let Ok(data) = data {
-                            cards = serde_json::from_str(&data).unwrap();
-                        } else {
-                            let data = reqwest::blocking::get("https://db.ygoprodeck.com/api/v7/cardinfo.php").unwrap().text().unwrap();
-                            cards = serde_json::from_str(&data).unwrap();
-                            std::fs::write(app_dirs.cache.join("cards.json"), data).unwrap();
-                        }
-                        let mut parsed_cards = Vec::new();
-                        for card in cards.data {
-                            parsed_cards.push(YugiohCard::from_raw(card));
-                        }
-                        parsed_cards
-                    }));
+                if let Some(error) = &self.load_error {
+                    ui.heading("Failed to load the card database");
+                    ui.label(error.to_string());
+                    if ui.button("Retry").clicked() {
+                        self.load_error = None;
+                    }
+                } else if self.p.is_none() {
+                    self.p = Some(self.load_cards());
+                    ui.spinner();
                 } else if self.p.as_ref().unwrap().ready().is_some() {
-                    self.cards = self.p.take().unwrap().block_and_take();
+                    match self.p.take().unwrap().block_and_take() {
+                        Ok(loaded) => {
+                            self.cards = loaded.cards;
+                            self.load_warning = loaded.warning.or(self.load_warning.take());
+                            self.open_deck(self.deck_path.clone());
+                            // keep the image cache within its limits, once per launch is plenty
+                            #[cfg(not(target_arch = "wasm32"))]
+                            self.spawn_cache_task(CacheTask::Prune);
+                        }
+                        Err(e) => self.load_error = Some(e),
+                    }
+                    self.request_repaint = true;
+                } else {
+                    let progress = self.load_progress.snapshot();
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label(progress.describe());
+                    });
+                    ui.add(egui::ProgressBar::new(progress.fraction().unwrap_or(0.0)).animate(progress.fraction().is_none()));
+                    self.request_repaint = true;
                 }
-                ui.spinner();
             } else {
-                if !self.deck.been_loaded {
-                    self.deck = YugiohDeck::from_file(self.app_dirs.appdata.join("deck.ydk"), &self.cards);
+                if let Some(warning) = &self.load_warning {
+                    let mut dismissed = false;
+                    ui.horizontal(|ui| {
+                        ui.colored_label(egui::Color32::YELLOW, warning);
+                        dismissed = ui.button("Dismiss").clicked();
+                    });
+                    if dismissed {
+                        self.load_warning = None;
+                    }
+                    ui.separator();
                 }
-                let r = ui.add(egui::Slider::new(&mut self.ppp, 1.0..=10.0).text("pixels per point"));
-                if !r.dragged() {
-                    ctx.set_pixels_per_point(self.ppp);
+                let image_progress = self.images.progress.snapshot();
+                if image_progress.total.is_some() && !image_progress.is_finished() {
+                    ui.add(egui::ProgressBar::new(image_progress.fraction().unwrap_or(0.0)).text(image_progress.describe()));
                 }
+                ui.horizontal(|ui| {
+                    let r = ui.add(egui::Slider::new(&mut self.ppp, 1.0..=10.0).text("pixels per point"));
+                    if !r.dragged() {
+                        ctx.set_pixels_per_point(self.ppp);
+                    }
+                    if ui.button("Settings").clicked() {
+                        self.show_settings = !self.show_settings;
+                    }
+                });
                 ui.separator();
                 self.last_search_criteria = self.search_criteria.clone();
                 self.last_sorting = self.sorting;
@@ -184,422 +1021,116 @@ impl eframe::App for App {
                             ui.label("Sorting");
                             ui.radio_value(&mut self.sorting.stype, SortingType::Name, "Name");
                             ui.radio_value(&mut self.sorting.stype, SortingType::Id, "Id");
-                            ui.radio_value(&mut self.sorting.order, Ord::Asc, "Asc");
-                            ui.radio_value(&mut self.sorting.order, Ord::Dsc, "Dsc");
+                            ui.radio_value(&mut self.sorting.order, SortOrder::Asc, "Asc");
+                            ui.radio_value(&mut self.sorting.order, SortOrder::Dsc, "Dsc");
                         });
                     });
                 });
                 if self.search_results.is_none() || self.last_search_criteria != self.search_criteria || self.last_sorting != self.sorting {
-                    let criteria = self.search_criteria.clone();
-                    let mut c: Vec<(usize, YugiohCard)> = Vec::new();
-                    // let deck = self.deck.clone();
-                    for (i, card) in self.cards.iter().enumerate() {
-                        if criteria.clone().matches(card) {
-                            // if deck.contains_card(i) != DeckType::None {
-                            //     c.push((i, card.clone()));
-                            // }
-                            c.push((i, card.clone()));
-                        }
-                    }
-                    let sorting = match self.sorting.stype {
-                        SortingType::Name => |a: &(usize, YugiohCard), b: &(usize, YugiohCard)| b.1.name.cmp(&a.1.name),
-                        SortingType::Id => |a: &(usize, YugiohCard), b: &(usize, YugiohCard)| b.1.id.cmp(&a.1.id),
-                    };
-                    if self.sorting.order == Ord::Dsc {
-                        c.sort_by(|a, b| sorting(b, a));
-                    } else {
-                        c.sort_by(sorting);
-                    }
-                    self.search_results = Some(c);
+                    self.search_results = Some(search::search(&self.cards, &self.search_criteria, self.sorting));
                 }
                 ui.separator();
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut self.list_display_mode, ListDisplayMode::Card, "Card");
+                    ui.selectable_value(&mut self.list_display_mode, ListDisplayMode::ImageOnly, "Image Only");
+                    ui.selectable_value(&mut self.list_display_mode, ListDisplayMode::Compact, "Compact");
+                });
+                ui.separator();
+                // cloned so rows can call back into self, it is only a list of indices
+                let search_results = self.search_results.clone().unwrap_or_default();
                 match self.list_display_mode {
                     ListDisplayMode::Card => {
-                        ui.label("Card");
-                        if ui.button("Image Only").clicked() {
-                            self.list_display_mode = ListDisplayMode::ImageOnly;
-                        }
-                        ui.separator();
-                        if let Some(search_results) = self.search_results.as_mut() {
-                            ScrollArea::vertical().show_rows(ui, CARD_HEIGHT, search_results.len(), |ui, range| {
-                                let input_position = ui.input().pointer.hover_pos();
-                                let mut card_to_draw = None;
-
-                                for i in range {
-                                    let rect = ui.allocate_space(egui::Vec2::new(ui.available_width(), CARD_HEIGHT + CARD_MARGIN));
-                                    let card = self.cards[search_results[i].0].as_mut();
-                                    let image_rect = egui::Rect::from_min_max(
-                                        rect.1.min + egui::Vec2::new(0., CARD_MARGIN),
-                                        rect.1.min + egui::Vec2::new(CARD_WIDTH - CARD_MARGIN, CARD_HEIGHT - CARD_MARGIN),
-                                    );
-                                    let text_rect = egui::Rect::from_min_max(
-                                        rect.1.min + egui::Vec2::new(CARD_WIDTH + CARD_MARGIN, CARD_MARGIN),
-                                        rect.1.max - egui::Vec2::new(CARD_MARGIN, CARD_MARGIN),
-                                    );
-                                    ui.painter().rect(
-                                        image_rect,
-                                        CARD_ROUNDING,
-                                        egui::Color32::from_rgb(54, 54, 54),
-                                        egui::Stroke::new(1., egui::Color32::from_rgb(64, 64, 64)),
-                                    );
-                                    ui.painter().rect(
-                                        text_rect,
-                                        CARD_ROUNDING,
-                                        egui::Color32::from_rgb(64, 64, 64),
-                                        egui::Stroke::new(1., egui::Color32::from_rgb(64, 64, 64)),
-                                    );
-                                    if let Some(image) = card.card_image.small.image {
-                                        let mut mesh = egui::Mesh::with_texture(image);
-                                        mesh.add_rect_with_uv(image_rect, egui::Rect::from_min_max(egui::Pos2::new(0., 0.), egui::Pos2::new(1., 1.)), egui::Color32::WHITE);
-                                        ui.painter().add(egui::Shape::Mesh(mesh));
-                                    } else if let Some(_promise_index) = card.card_image.small.promise_index {
-                                        ui.painter().text(
-                                            image_rect.center(),
-                                            egui::Align2::CENTER_CENTER,
-                                            "Loading...",
-                                            egui::FontId::default(),
-                                            egui::Color32::from_rgb(0, 0, 0),
-                                        );
-                                        self.request_repaint = true;
-                                    // let im = self.image_promises.get_ref(promise_index);
-                                    // if let Some(im) = im {
-                                    //  if im.poll().is_ready() {
-                                    //      let im = self.image_promises.try_take(promise_index);
-                                    //      if let Some(im) = im {
-                                    //       let im = im.block_and_take();
-                                    //       if let Ok(im) = im {
-                                    //           card.card_image.small.image = Some(im.texture_id(ctx));
-                                    //           card.card_image.small.promise_index = None;
-                                    //           self.cached_images.push(im);
-                                    //       } else {
-                                    //           eprintln!("Image promise exists but was not fulfilled");
-                                    //       }
-                                    //      } else {
-                                    //       eprintln!("Image promise could not be taken");
-                                    //      }
-                                    //  } else {
-                                    //      //eprintln!("Image promise is not ready");
-                                    //  }
-                                    // } else {
-                                    //  eprintln!("Image promise does not exist");
-                                    //  card.card_image.small.promise_index = None;
-                                    // }
-                                    } else if self.image_promises.get_index().is_some() {
-                                        let i = self.image_promises.try_add(card.card_image.small.get_promise(self.api_override, self.app_dirs.small.clone()));
-                                        if let Ok(i) = i {
-                                            card.card_image.small.promise_index = Some(i);
-                                        } else {
-                                            eprintln!("Failed to create promise for small image: {}", card.id);
-                                        }
-                                    } else {
-                                        // eprintln!("Failed to get index for image promise");
-                                    }
-
-                                    if let Some(input_position) = input_position {
-                                        if image_rect.contains(input_position) {
-                                            card_to_draw = Some((card.clone(), image_rect));
-                                        }
-                                    }
+                        ui.label("Click a card for details, right click to add it to the deck");
+                        ScrollArea::vertical().show_rows(ui, CARD_HEIGHT, search_results.len(), |ui, range| {
+                            let prefetch = prefetch_rows(range.clone(), self.prefetch_rows, search_results.len());
+                            for i in range {
+                                let response = self.card_row(ui, search_results[i], ImageSize::Small, CARD_HEIGHT, None);
+                                if response.clicked() {
+                                    self.detail_card = Some(search_results[i]);
+                                } else if response.secondary_clicked() {
+                                    self.add_to_deck(search_results[i]);
                                 }
-                                if let Some((card, image_rect)) = card_to_draw {
-                                    let card = self.cards.iter_mut().find(|c| c.id == card.id).unwrap();
-                                    if let Some(input_position) = input_position {
-                                        if let Some(image) = card.card_image.large.image {
-                                            let min_x = input_position.x;
-                                            let ui_rect = ui.clip_rect();
-                                            let max_x = ui_rect.max.y * ASPECT_RATIO;
-                                            let bigimage_rect = if max_x < ui_rect.max.x - min_x {
-                                                let min_y = ui_rect.min.y;
-                                                let max_y = ui_rect.max.y;
-                                                let max_x = input_position.x + ((max_y - min_y) * ASPECT_RATIO);
-                                                egui::Rect::from_min_max(egui::Pos2::new(min_x, min_y), egui::Pos2::new(max_x, max_y))
-                                            } else if input_position.y < ui_rect.center().y {
-                                                let min_y = ui_rect.min.y;
-                                                let max_x = ui_rect.max.x;
-                                                let max_y = min_y + (max_x - min_x) / ASPECT_RATIO;
-                                                egui::Rect::from_min_max(egui::Pos2::new(min_x, min_y), egui::Pos2::new(max_x, max_y))
-                                            } else {
-                                                let max_y = ui_rect.max.y;
-                                                let max_x = ui_rect.max.x;
-                                                let min_y = max_y - (max_x - min_x) / ASPECT_RATIO;
-                                                egui::Rect::from_min_max(egui::Pos2::new(min_x, min_y), egui::Pos2::new(max_x, max_y))
-                                            };
-                                            let mut mesh = egui::Mesh::with_texture(image);
-                                            mesh.add_rect_with_uv(bigimage_rect, egui::Rect::from_min_max(egui::Pos2::ZERO, egui::Pos2::new(1., 1.)), egui::Color32::WHITE);
-                                            ui.painter().add(egui::Shape::Mesh(mesh));
-                                        } else if let Some(_promise_index) = card.card_image.large.promise_index {
-                                            ui.painter().text(
-                                                image_rect.center(),
-                                                egui::Align2::CENTER_CENTER,
-                                                "Loading...",
-                                                egui::FontId::default(),
-                                                egui::Color32::from_rgb(0, 0, 0),
-                                            );
-                                            self.request_repaint = true;
-                                        // let im = self.image_promises.get_ref(promise_index);
-                                        // if let Some(im) = im {
-                                        //  if im.poll().is_ready() {
-                                        //      let im = self.image_promises.try_take(promise_index);
-                                        //      if let Some(im) = im {
-                                        //       let im = im.block_and_take();
-                                        //       if let Ok(im) = im {
-                                        //           card.card_image.large.image = Some(im.texture_id(ctx));
-                                        //           card.card_image.large.promise_index = None;
-                                        //           self.cached_images.push(im);
-                                        //       } else {
-                                        //           eprintln!("Image promise exists but was not fulfilled");
-                                        //       }
-                                        //      } else {
-                                        //       eprintln!("Image promise could not be taken");
-                                        //      }
-                                        //  } else {
-                                        //      //eprintln!("Image promise is not ready");
-                                        //  }
-                                        // } else {
-                                        //  eprintln!("Image promise does not exist");
-                                        //  card.card_image.large.promise_index = None;
-                                        // }
-                                        } else if self.image_promises.get_index().is_some() {
-                                            let i = self.image_promises.try_add(card.card_image.large.get_promise(self.api_override, self.app_dirs.large.clone()));
-                                            if let Ok(i) = i {
-                                                card.card_image.large.promise_index = Some(i);
-                                            } else {
-                                                eprintln!("Failed to create promise for large image: {}", card.id);
-                                            }
-                                        } else {
-                                            // eprintln!("Failed to get index for image promise");
-                                        }
-                                    }
+                            }
+                            for i in prefetch {
+                                self.images.prefetch(&self.cards[search_results[i]].card_image, ImageSize::Small, self.network_policy);
+                            }
+                        });
+                    }
+                    ListDisplayMode::Compact => {
+                        ui.label("Click a card for details, right click to add it to the deck");
+                        ScrollArea::vertical().show_rows(ui, COMPACT_HEIGHT, search_results.len(), |ui, range| {
+                            let prefetch = prefetch_rows(range.clone(), self.prefetch_rows, search_results.len());
+                            for i in range {
+                                let response = self.card_row(ui, search_results[i], ImageSize::Cropped, COMPACT_HEIGHT, None);
+                                if response.clicked() {
+                                    self.detail_card = Some(search_results[i]);
+                                } else if response.secondary_clicked() {
+                                    self.add_to_deck(search_results[i]);
                                 }
-                            });
-                        }
+                            }
+                            for i in prefetch {
+                                self.images.prefetch(&self.cards[search_results[i]].card_image, ImageSize::Cropped, self.network_policy);
+                            }
+                        });
                     }
                     ListDisplayMode::ImageOnly => {
-                        ui.label("Image Only");
-                        if ui.button("Card").clicked() {
-                            self.list_display_mode = ListDisplayMode::Card;
-                        }
-                        ui.separator();
-                        if let Some(search_results) = self.search_results.as_mut() {
-                            // determine how many columns we can fit based on the width of the window and the CARD_WIDTH
-
-                            let width = ui.available_rect_before_wrap().width();
-                            // ui.painter().rect_stroke(ui.available_rect_before_wrap(), 1.0, egui::Stroke::new(1.0, egui::Color32::from_rgb(0, 0, 0)));
-                            let split = width / (CARD_WIDTH + CARD_MARGIN);
-                            let columns = split.floor() as usize;
-                            let scaling = (split - (columns as f32 * 0.0885)) / columns as f32;
-
-                            let mut card_to_draw = None;
-                            let input_position = ui.input().pointer.hover_pos();
-                            ScrollArea::vertical().show_rows(
-                                ui,
-                                (CARD_HEIGHT + CARD_MARGIN) * scaling,
-                                (search_results.len() as f32 / columns as f32).ceil() as usize,
-                                |ui, range| {
-                                    // this will be a grid of cards, the calculated number of columns wide and the calculated number of rows high
-
-                                    for row in range {
-                                        ui.horizontal(|ui| {
-                                            for column in 0..columns {
-                                                let index = row * columns + column;
-                                                if index < search_results.len() {
-                                                    let card = self.cards[search_results[index].0].as_mut();
-                                                    let indeck = self.deck.contains_card(search_results[index].0);
-                                                    let color = match indeck {
-                                                        DeckType::Main => Some(egui::Color32::from_rgb(255, 255, 0)),
-                                                        DeckType::Side => Some(egui::Color32::from_rgb(0, 255, 255)),
-                                                        DeckType::Extra => Some(egui::Color32::from_rgb(255, 0, 255)),
-                                                        DeckType::None => None,
-                                                    };
-                                                    let (card_image, correct_dir) = if ctx.pixels_per_point() < 3.5 {
-                                                        (&mut card.card_image.small, self.app_dirs.small.clone())
-                                                    } else {
-                                                        (&mut card.card_image.large, self.app_dirs.large.clone())
-                                                    };
-                                                    // let card_image = &mut card.card_image.small;
-                                                    let (_id, rect) = ui.allocate_space(egui::Vec2::new((CARD_WIDTH + CARD_MARGIN) * scaling, (CARD_HEIGHT + CARD_MARGIN) * scaling));
-                                                    ui.painter()
-                                                        .rect(rect, CARD_ROUNDING, egui::Color32::from_rgb(54, 54, 54), egui::Stroke::new(1., egui::Color32::from_rgb(64, 64, 64)));
-                                                    if let Some(image) = card_image.image {
-                                                        let mut mesh = egui::Mesh::with_texture(image);
-                                                        mesh.add_rect_with_uv(rect, egui::Rect::from_min_max(egui::Pos2::new(0., 0.), egui::Pos2::new(1., 1.)), egui::Color32::WHITE);
-                                                        ui.painter().add(egui::Shape::Mesh(mesh));
-                                                    } else if let Some(_promise_index) = card_image.promise_index {
-                                                        ui.painter()
-                                                            .text(rect.center(), egui::Align2::CENTER_CENTER, "Loading...", egui::FontId::default(), egui::Color32::from_rgb(0, 0, 0));
-                                                        self.request_repaint = true;
-                                                    // let im = self.image_promises.get_ref(promise_index);
-                                                    // if let Some(im) = im {
-                                                    //  if im.poll().is_ready() {
-                                                    //      let im = self.image_promises.try_take(promise_index);
-                                                    //      if let Some(im) = im {
-                                                    //       let im = im.block_and_take();
-                                                    //       if let Ok(im) = im {
-                                                    //           card.card_image.small.image = Some(im.texture_id(ctx));
-                                                    //           card.card_image.small.promise_index = None;
-                                                    //           self.cached_images.push(im);
-                                                    //       } else {
-                                                    //           eprintln!("Image promise exists but was not fulfilled");
-                                                    //       }
-                                                    //      } else {
-                                                    //       eprintln!("Image promise could not be taken");
-                                                    //      }
-                                                    //  } else {
-                                                    //      //eprintln!("Image promise is not ready");
-                                                    //  }
-                                                    // } else {
-                                                    //  eprintln!("Image promise does not exist");
-                                                    //  card.card_image.small.promise_index = None;
-                                                    // }
-                                                    } else if self.image_promises.get_index().is_some() {
-                                                        let i = self.image_promises.try_add(card_image.get_promise(self.api_override, correct_dir));
-                                                        if let Ok(i) = i {
-                                                            card_image.promise_index = Some(i);
-                                                        } else {
-                                                            eprintln!("Failed to create promise for small image: {}", card.id);
-                                                        }
-                                                    } else {
-                                                        // eprintln!("Failed to get index for image promise");
-                                                    }
-
-                                                    if let Some(input_position) = input_position {
-                                                        if rect.contains(input_position) {
-                                                            card_to_draw = Some((card.clone(), rect));
-                                                        }
-                                                    }
-                                                    if let Some(color) = color {
-                                                        ui.painter().rect_stroke(rect, CARD_ROUNDING, egui::Stroke::new(1., color));
-                                                    }
-                                                }
-                                            }
-                                        });
-                                    }
-                                    if let Some((card, image_rect)) = card_to_draw {
-                                        let card = self.cards.iter_mut().find(|c| c.id == card.id).unwrap();
-                                        if let Some(input_position) = input_position {
-                                            if let Some(image) = card.card_image.large.image {
-                                                ui.painter().rect(
-                                                    image_rect,
-                                                    CARD_ROUNDING,
-                                                    egui::Color32::from_rgb(54, 54, 54),
-                                                    egui::Stroke::new(1., egui::Color32::from_rgb(64, 64, 64)),
-                                                );
-                                                let bigimage_rect = if input_position.x < ui.available_width() / 2. {
-                                                    let min_x = input_position.x;
-                                                    let ui_rect = ui.clip_rect();
-                                                    let max_x = ui_rect.max.y * ASPECT_RATIO;
-                                                    if max_x < ui_rect.max.x - min_x {
-                                                        let min_y = ui_rect.min.y;
-                                                        let max_y = ui_rect.max.y;
-                                                        let max_x = input_position.x + ((max_y - min_y) * ASPECT_RATIO);
-                                                        egui::Rect::from_min_max(egui::Pos2::new(min_x, min_y), egui::Pos2::new(max_x, max_y))
-                                                    } else if input_position.y < ui_rect.center().y {
-                                                        let min_y = ui_rect.min.y;
-                                                        let max_x = ui_rect.max.x;
-                                                        let max_y = min_y + (max_x - min_x) / ASPECT_RATIO;
-                                                        egui::Rect::from_min_max(egui::Pos2::new(min_x, min_y), egui::Pos2::new(max_x, max_y))
-                                                    } else {
-                                                        let max_y = ui_rect.max.y;
-                                                        let max_x = ui_rect.max.x;
-                                                        let min_y = max_y - (max_x - min_x) / ASPECT_RATIO;
-                                                        egui::Rect::from_min_max(egui::Pos2::new(min_x, min_y), egui::Pos2::new(max_x, max_y))
-                                                    }
-                                                } else {
-                                                    let max_x = input_position.x;
-                                                    let ui_rect = ui.clip_rect();
-                                                    let min_x = ui_rect.min.y * ASPECT_RATIO;
-                                                    if min_x > ui_rect.min.x - max_x {
-                                                        let min_y = ui_rect.min.y;
-                                                        let max_y = ui_rect.max.y;
-                                                        let min_x = input_position.x - ((max_y - min_y) * ASPECT_RATIO);
-                                                        egui::Rect::from_min_max(egui::Pos2::new(min_x, min_y), egui::Pos2::new(max_x, max_y))
-                                                    } else if input_position.y < ui_rect.center().y {
-                                                        let min_y = ui_rect.min.y;
-                                                        let min_x = ui_rect.min.x;
-                                                        let max_y = min_y + (max_x - min_x) / ASPECT_RATIO;
-                                                        egui::Rect::from_min_max(egui::Pos2::new(min_x, min_y), egui::Pos2::new(max_x, max_y))
-                                                    } else {
-                                                        let max_y = ui_rect.max.y;
-                                                        let min_x = ui_rect.min.x;
-                                                        let min_y = max_y - (max_x - min_x) / ASPECT_RATIO;
-                                                        egui::Rect::from_min_max(egui::Pos2::new(min_x, min_y), egui::Pos2::new(max_x, max_y))
-                                                    }
-                                                };
-                                                let mut mesh = egui::Mesh::with_texture(image);
-                                                mesh.add_rect_with_uv(bigimage_rect, egui::Rect::from_min_max(egui::Pos2::ZERO, egui::Pos2::new(1., 1.)), egui::Color32::WHITE);
-                                                ui.painter().add(egui::Shape::Mesh(mesh));
-                                            } else if let Some(_promise_index) = card.card_image.large.promise_index {
-                                                ui.painter().text(
-                                                    image_rect.center(),
-                                                    egui::Align2::CENTER_CENTER,
-                                                    "Loading...",
-                                                    egui::FontId::default(),
-                                                    egui::Color32::from_rgb(0, 0, 0),
-                                                );
-                                                self.request_repaint = true;
-                                            // let im = self.image_promises.get_ref(promise_index);
-                                            // if let Some(im) = im {
-                                            //     if im.poll().is_ready() {
-                                            //         let im = self.image_promises.try_take(promise_index);
-                                            //         if let Some(im) = im {
-                                            //             let im = im.block_and_take();
-                                            //             if let Ok(im) = im {
-                                            //                 card.card_image.large.image = Some(im.texture_id(ctx));
-                                            //                 card.card_image.large.promise_index = None;
-                                            //                 self.cached_images.push(im);
-                                            //             } else {
-                                            //                 eprintln!("Image promise exists but was not fulfilled");
-                                            //             }
-                                            //         } else {
-                                            //             eprintln!("Image promise could not be taken");
-                                            //         }
-                                            //     } else {
-                                            //         //eprintln!("Image promise is not ready");
-                                            //     }
-                                            // } else {
-                                            //     eprintln!("Image promise does not exist");
-                                            //     card.card_image.large.promise_index = None;
-                                            // }
-                                            } else if self.image_promises.get_index().is_some() {
-                                                let i = self.image_promises.try_add(card.card_image.large.get_promise(self.api_override, self.app_dirs.large.clone()));
-                                                if let Ok(i) = i {
-                                                    card.card_image.large.promise_index = Some(i);
-                                                } else {
-                                                    eprintln!("Failed to create promise for large image: {}", card.id);
-                                                }
-                                            } else {
-                                                // eprintln!("Failed to get index for image promise");
-                                            }
+                        ui.label("Click a card for details, right click to add it to the deck");
+                        // determine how many columns we can fit based on the width of the window and the CARD_WIDTH
+                        let width = ui.available_rect_before_wrap().width();
+                        let split = width / (CARD_WIDTH + CARD_MARGIN);
+                        let columns = (split.floor() as usize).max(1);
+                        let scaling = (split - (columns as f32 * 0.0885)) / columns as f32;
+                        let tile_size = egui::Vec2::new(CARD_WIDTH + CARD_MARGIN, CARD_HEIGHT + CARD_MARGIN) * scaling;
+                        let rows = (search_results.len() as f32 / columns as f32).ceil() as usize;
+                        // small images get blurry once the ui is scaled up far enough
+                        let image_size = if ctx.pixels_per_point() < 3.5 { ImageSize::Small } else { ImageSize::Large };
+                        ScrollArea::vertical().show_rows(ui, tile_size.y, rows, |ui, range| {
+                            // this will be a grid of cards, the calculated number of columns wide and the calculated number of rows high
+                            let prefetch = prefetch_rows(range.clone(), self.prefetch_rows, rows);
+                            for row in range {
+                                ui.horizontal(|ui| {
+                                    for index in (row * columns..(row + 1) * columns).filter(|index| *index < search_results.len()) {
+                                        let card = search_results[index];
+                                        let outline = tile::deck_color(self.deck.contains_card(card));
+                                        let response = ui.add(CardTile::new(&mut self.images, &self.cards[card], image_size, tile_size).outline(outline).policy(self.network_policy));
+                                        if response.hovered() {
+                                            self.preview_card = Some((card, response.rect));
+                                        }
+                                        if response.clicked() {
+                                            self.detail_card = Some(card);
+                                        } else if response.secondary_clicked() {
+                                            self.add_to_deck(card);
                                         }
                                     }
-                                },
-                            );
-                        }
-                    }
-                }
-            }
-            let mut did = false;
-            for card in self.cards.iter_mut() {
-                let r = card.card_image.check_promises(ctx, &mut self.image_promises, false);
-                for i in r {
-                    if i.0 {
-                        did = true;
-                        if let Ok(im) = i.1 {
-                            if let Some(im) = im {
-                                self.cached_images.push(im);
-                            } else {
-                                // eprintln!("Image promise exists but was not fulfilled");
+                                });
                             }
-                        } else {
-                            eprintln!("{}", i.1.err().unwrap());
-                        }
+                            for row in prefetch {
+                                for index in (row * columns..(row + 1) * columns).filter(|index| *index < search_results.len()) {
+                                    self.images.prefetch(&self.cards[search_results[index]].card_image, image_size, self.network_policy);
+                                }
+                            }
+                        });
                     }
                 }
             }
-            if !did {
-                // self.image_promises.clear();
-            }
         });
+        if !self.cards.is_empty() {
+            self.history_shortcuts(ctx);
+            self.history_window(ctx);
+            self.compare_window(ctx);
+            self.side_plans_window(ctx);
+            self.deck_info_window(ctx);
+        }
+        self.settings_window(ctx);
+        self.card_detail_window(ctx);
+        self.save_settings(ctx);
+        if let Some((card, rect)) = self.preview_card.take() {
+            tile::show_preview(ctx, &mut self.images, &self.cards[card], rect, self.network_policy);
+        }
+        // after every window, so images drawn in them count as visible
+        for e in self.images.end_frame(ctx) {
+            eprintln!("{}", e);
+        }
         if self.request_repaint {
             self.request_repaint = false;
             ctx.request_repaint();
@@ -0,0 +1,232 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{deck, validation::Format, yugioh::YugiohCard};
+
+// all of it goes on one "##meta {json}" line near the top of the .ydk, a second one would replace the first
+pub const META_TAG: &str = "meta";
+
+// the roles offered for cards, any other text works as a role too
+pub const CARD_ROLES: [&str; 7] = ["Starter", "Extender", "Hand trap", "Board breaker", "Engine", "Tech", "Brick"];
+
+// everything about a deck that is not its cards
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeckMetadata {
+    pub name: String,
+    pub author: String,
+    // the format the deck is built for, None if it was never picked
+    pub format: Option<Format>,
+    pub tags: Vec<String>,
+    pub notes: String,
+    // unix seconds, set by whatever saves the deck since the library has no clock that works everywhere
+    pub created: Option<u64>,
+    pub modified: Option<u64>,
+    // role tags by card index, cards without roles are not in here
+    pub card_roles: BTreeMap<usize, Vec<String>>,
+}
+
+// every field may be missing so a line written by hand, or before a field existed, still loads
+// the roles map is keyed by passcode, json turns those keys into strings
+#[derive(Serialize, Deserialize)]
+struct StoredMetadata {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    author: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    format: Option<Format>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    notes: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    created: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    modified: Option<u64>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    roles: BTreeMap<u32, Vec<String>>,
+}
+
+impl DeckMetadata {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    // stamps a save, the first one ever also counts as when the deck was created
+    pub fn touch(&mut self, now: u64) {
+        self.created.get_or_insert(now);
+        self.modified = Some(now);
+    }
+
+    pub fn roles(&self, card: usize) -> &[String] {
+        self.card_roles.get(&card).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    // adds the role if the card does not have it yet and takes it away if it does
+    pub fn toggle_role(&mut self, card: usize, role: &str) {
+        let roles = self.card_roles.entry(card).or_default();
+        match roles.iter().position(|r| r.eq_ignore_ascii_case(role)) {
+            Some(position) => {
+                roles.remove(position);
+            }
+            None => roles.push(role.to_owned()),
+        }
+        if roles.is_empty() {
+            self.card_roles.remove(&card);
+        }
+    }
+
+    // every role with the cards that have it, in the order the roles were first given out
+    pub fn cards_by_role(&self) -> Vec<(String, Vec<usize>)> {
+        let mut by_role: Vec<(String, Vec<usize>)> = Vec::new();
+        for (card, roles) in &self.card_roles {
+            for role in roles {
+                match by_role.iter_mut().find(|(r, _)| r.eq_ignore_ascii_case(role)) {
+                    Some((_, cards)) => cards.push(*card),
+                    None => by_role.push((role.clone(), vec![*card])),
+                }
+            }
+        }
+        by_role
+    }
+
+    pub fn to_comment(&self, cards: &[YugiohCard]) -> String {
+        let stored = StoredMetadata {
+            name: self.name.clone(),
+            author: self.author.clone(),
+            format: self.format,
+            tags: self.tags.clone(),
+            notes: self.notes.clone(),
+            created: self.created,
+            modified: self.modified,
+            roles: self.card_roles.iter().map(|(card, roles)| (cards[*card].id, roles.clone())).collect(),
+        };
+        deck::to_data_comment(META_TAG, &stored)
+    }
+
+    // a role given to a card the card list no longer has is lost, there is nothing to show it on
+    pub fn from_comment(line: &str, cards: &[YugiohCard]) -> Option<Self> {
+        let stored: StoredMetadata = deck::from_data_comment(line, META_TAG)?;
+        Some(Self {
+            name: stored.name,
+            author: stored.author,
+            format: stored.format,
+            tags: stored.tags,
+            notes: stored.notes,
+            created: stored.created,
+            modified: stored.modified,
+            card_roles: stored.roles.into_iter().filter_map(|(id, roles)| Some((deck::card_index(cards, id)?, roles))).collect(),
+        })
+    }
+}
+
+// "a, b,c" as tags, blank ones and repeats are dropped
+pub fn parse_tags(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in text.split(',').map(str::trim).filter(|tag| !tag.is_empty()) {
+        if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.to_owned());
+        }
+    }
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        deck::YugiohDeck,
+        testcards::{cards, deck, BLUE_EYES, DARK_MAGICIAN, POT_OF_GREED, ULTIMATE_DRAGON},
+    };
+
+    fn deck_with(metadata: DeckMetadata) -> YugiohDeck {
+        let mut deck = deck(&[BLUE_EYES, DARK_MAGICIAN], &[ULTIMATE_DRAGON], &[]);
+        deck.metadata = metadata;
+        deck
+    }
+
+    #[test]
+    fn metadata_survives_a_ydk_round_trip() {
+        let cards = cards();
+        let mut metadata = DeckMetadata {
+            name: "Blue-Eyes #main !side".to_owned(),
+            author: "Kaiba".to_owned(),
+            format: Some(Format::Goat),
+            tags: vec!["budget, but fun".to_owned(), "locals".to_owned()],
+            notes: "first line\n#extra on the second\n\n\"quoted\" and \\ backslash".to_owned(),
+            created: Some(1_700_000_000),
+            modified: Some(1_760_000_000),
+            card_roles: BTreeMap::new(),
+        };
+        metadata.toggle_role(BLUE_EYES, "Starter");
+        metadata.toggle_role(ULTIMATE_DRAGON, "Tech");
+        metadata.toggle_role(ULTIMATE_DRAGON, "Brick");
+        let saved = deck_with(metadata);
+        let text = saved.to_ydk(&cards);
+        assert_eq!(text.lines().filter(|line| line.starts_with("##meta ")).count(), 1);
+        assert_eq!(YugiohDeck::from_ydk(&text, &cards), saved);
+    }
+
+    #[test]
+    fn roles_of_cards_that_left_the_deck_are_not_written() {
+        let cards = cards();
+        let mut metadata = DeckMetadata::default();
+        metadata.toggle_role(BLUE_EYES, "Starter");
+        metadata.toggle_role(POT_OF_GREED, "Brick");
+        let mut saved = deck_with(metadata);
+        let loaded = YugiohDeck::from_ydk(&saved.to_ydk(&cards), &cards);
+        assert_eq!(loaded.metadata.card_roles, BTreeMap::from([(BLUE_EYES, vec!["Starter".to_owned()])]));
+        // with no roles left there is nothing to write at all
+        saved.metadata.toggle_role(BLUE_EYES, "Starter");
+        assert!(!saved.to_ydk(&cards).contains("##meta"));
+    }
+
+    #[test]
+    fn empty_metadata_is_not_written() {
+        let cards = cards();
+        assert!(!deck_with(DeckMetadata::default()).to_ydk(&cards).contains("##meta"));
+    }
+
+    #[test]
+    fn missing_fields_and_unknown_cards_are_left_out() {
+        let cards = cards();
+        let text = "#main\n89631139\n##meta {\"name\":\"Hand written\",\"roles\":{\"89631139\":[\"Starter\"],\"12345\":[\"Tech\"]}}\n";
+        let metadata = YugiohDeck::from_ydk(text, &cards).metadata;
+        assert_eq!(metadata.name, "Hand written");
+        assert_eq!(metadata.format, None);
+        assert_eq!(metadata.created, None);
+        assert_eq!(metadata.card_roles, BTreeMap::from([(BLUE_EYES, vec!["Starter".to_owned()])]));
+    }
+
+    #[test]
+    fn touch_keeps_the_first_save_as_created() {
+        let mut metadata = DeckMetadata::default();
+        metadata.touch(10);
+        metadata.touch(20);
+        assert_eq!((metadata.created, metadata.modified), (Some(10), Some(20)));
+    }
+
+    #[test]
+    fn toggling_roles() {
+        let mut metadata = DeckMetadata::default();
+        metadata.toggle_role(BLUE_EYES, "Starter");
+        metadata.toggle_role(BLUE_EYES, "Extender");
+        metadata.toggle_role(DARK_MAGICIAN, "starter");
+        assert_eq!(metadata.roles(BLUE_EYES), ["Starter", "Extender"]);
+        assert_eq!(
+            metadata.cards_by_role(),
+            vec![("Starter".to_owned(), vec![BLUE_EYES, DARK_MAGICIAN]), ("Extender".to_owned(), vec![BLUE_EYES])]
+        );
+        // roles match whatever their case, and a card without any is forgotten
+        metadata.toggle_role(DARK_MAGICIAN, "STARTER");
+        assert!(metadata.roles(DARK_MAGICIAN).is_empty());
+        assert!(!metadata.card_roles.contains_key(&DARK_MAGICIAN));
+    }
+
+    #[test]
+    fn tags_from_text() {
+        assert_eq!(parse_tags(" budget, locals,, Budget ,goat "), ["budget", "locals", "goat"]);
+        assert!(parse_tags(" , ").is_empty());
+    }
+}
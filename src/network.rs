@@ -0,0 +1,74 @@
+use std::{path::Path, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::diskcache;
+
+// how old cached data may get before RefreshStale downloads it again
+pub const CARD_DATA_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+// card art hardly ever changes, so images may get a lot older than the card data
+pub const IMAGE_MAX_AGE: Duration = Duration::from_secs(90 * 24 * 60 * 60);
+
+// when the card database and card images may be downloaded instead of read from the disk cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum NetworkPolicy {
+    // never touch the network, anything that is not cached is unavailable
+    OfflineOnly,
+    // only download what is not cached yet
+    #[default]
+    CacheFirst,
+    // also download again whatever was cached too long ago, the old copy is kept if that fails
+    RefreshStale,
+    // always download, the cache is only used when downloading fails
+    ForceRefresh,
+}
+
+impl NetworkPolicy {
+    pub const ALL: [NetworkPolicy; 4] = [NetworkPolicy::OfflineOnly, NetworkPolicy::CacheFirst, NetworkPolicy::RefreshStale, NetworkPolicy::ForceRefresh];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            NetworkPolicy::OfflineOnly => "Offline only",
+            NetworkPolicy::CacheFirst => "Cache first",
+            NetworkPolicy::RefreshStale => "Refresh stale",
+            NetworkPolicy::ForceRefresh => "Force refresh",
+        }
+    }
+
+    // accepts the names the command line uses, like "cache-first"
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().replace(['-', '_', ' '], "").as_str() {
+            "offline" | "offlineonly" => Some(NetworkPolicy::OfflineOnly),
+            "cachefirst" => Some(NetworkPolicy::CacheFirst),
+            "refreshstale" => Some(NetworkPolicy::RefreshStale),
+            "forcerefresh" => Some(NetworkPolicy::ForceRefresh),
+            _ => None,
+        }
+    }
+
+    pub fn allows_network(&self) -> bool {
+        *self != NetworkPolicy::OfflineOnly
+    }
+
+    // whether a cached file can be used as is, without asking the network for a newer copy first
+    pub fn use_cached(&self, path: &Path, max_age: Duration) -> bool {
+        self.use_cached_age(cached_age(path), max_age)
+    }
+
+    // the same for cached data whose age is known some other way
+    pub fn use_cached_age(&self, age: Option<Duration>, max_age: Duration) -> bool {
+        match self {
+            NetworkPolicy::OfflineOnly | NetworkPolicy::CacheFirst => true,
+            // data whose age can not be read is treated as fresh, there is no point in downloading it over and over
+            NetworkPolicy::RefreshStale => age.map(|age| age <= max_age).unwrap_or(true),
+            NetworkPolicy::ForceRefresh => false,
+        }
+    }
+}
+
+// how long ago a cached file was downloaded, never the modification time since pruning bumps that on every use
+// files cached before download times were recorded fall back to their creation time where the platform has one
+pub fn cached_age(path: &Path) -> Option<Duration> {
+    let downloaded = diskcache::downloaded_at(path).or_else(|| std::fs::metadata(path).ok()?.created().ok())?;
+    downloaded.elapsed().ok()
+}
@@ -0,0 +1,132 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use eframe::egui;
+use poll_promise::Promise;
+use ygodeckbuilderproelite::{
+    bulk::{self, BulkOptions, BulkReport, BulkScope},
+    progress::{Progress, ProgressUnit},
+    source::CardSource,
+    AppDirs, YugiohCard, YugiohDeck,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Target {
+    All,
+    Deck,
+    Archetype,
+    Set,
+}
+
+// the settings section that fills the disk cache ahead of time so the app works without a connection
+pub struct OfflineDownload {
+    target: Target,
+    filter: String,
+    large: bool,
+    cropped: bool,
+    requests_per_second: f32,
+    // the cards the last scope selected, going through every card's sets on each frame is too slow
+    // kept with the scope and the number of cards it was made from, so a reloaded card database selects again
+    selection: Option<(BulkScope, usize, Vec<usize>)>,
+    job: Option<Promise<BulkReport>>,
+    cancel: Arc<AtomicBool>,
+    progress: Progress,
+    message: Option<String>,
+}
+
+impl OfflineDownload {
+    pub fn new() -> Self {
+        Self {
+            target: Target::All,
+            filter: String::new(),
+            large: false,
+            cropped: false,
+            requests_per_second: bulk::DEFAULT_REQUESTS_PER_SECOND,
+            selection: None,
+            job: None,
+            cancel: Arc::new(AtomicBool::new(false)),
+            progress: Progress::new("Downloading images", ProgressUnit::Items),
+            message: None,
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui, cards: &[YugiohCard], deck: &YugiohDeck, app_dirs: &AppDirs, source: &Arc<dyn CardSource>) {
+        if let Some(job) = &self.job {
+            if let Some(report) = job.ready() {
+                self.message = Some(format!(
+                    "{} {} downloaded, {} made from large images, {} already cached, {} failed",
+                    if report.cancelled { "Cancelled:" } else { "Done:" },
+                    report.downloaded,
+                    report.derived,
+                    report.skipped,
+                    report.failed
+                ));
+                self.job = None;
+            }
+        }
+        if self.job.is_some() {
+            let progress = self.progress.snapshot();
+            ui.add(egui::ProgressBar::new(progress.fraction().unwrap_or(0.0)).text(progress.describe()));
+            if ui.button("Cancel").clicked() {
+                self.cancel.store(true, Ordering::Relaxed);
+            }
+            ui.ctx().request_repaint();
+            return;
+        }
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.target, Target::All, "Every card");
+            ui.radio_value(&mut self.target, Target::Deck, "Current deck");
+            ui.radio_value(&mut self.target, Target::Archetype, "Archetype");
+            ui.radio_value(&mut self.target, Target::Set, "Set");
+        });
+        if matches!(self.target, Target::Archetype | Target::Set) {
+            ui.horizontal(|ui| {
+                ui.label(if self.target == Target::Set { "Set name or code" } else { "Archetype" });
+                ui.text_edit_singleline(&mut self.filter);
+            });
+        }
+        ui.checkbox(&mut self.large, "Include large images");
+        ui.checkbox(&mut self.cropped, "Include cropped artwork");
+        ui.add(egui::Slider::new(&mut self.requests_per_second, 1.0..=20.0).text("requests per second"));
+        let scope = match self.target {
+            Target::All => BulkScope::All,
+            Target::Deck => BulkScope::deck(deck),
+            Target::Archetype => BulkScope::Archetype(self.filter.trim().to_owned()),
+            Target::Set => BulkScope::Set(self.filter.trim().to_owned()),
+        };
+        let selected = match &self.selection {
+            Some((selected_scope, len, selected)) if *selected_scope == scope && *len == cards.len() => selected,
+            _ => {
+                let selected = scope.select(cards);
+                &self.selection.insert((scope, cards.len(), selected)).2
+            }
+        };
+        ui.horizontal(|ui| {
+            if ui.add_enabled(!selected.is_empty(), egui::Button::new("Download")).clicked() {
+                // only the selected cards go to the worker, that is usually far less than the whole database
+                let cards: Vec<YugiohCard> = selected.iter().map(|i| cards[*i].clone()).collect();
+                let options = BulkOptions {
+                    scope: BulkScope::All,
+                    large: self.large,
+                    cropped: self.cropped,
+                    requests_per_second: self.requests_per_second,
+                };
+                let app_dirs = app_dirs.clone();
+                let source = source.clone();
+                let progress = self.progress.clone();
+                self.cancel.store(false, Ordering::Relaxed);
+                let cancel = self.cancel.clone();
+                self.message = None;
+                self.job = Some(Promise::spawn_thread("offline images", move || {
+                    bulk::download(&cards, &options, &app_dirs, source.as_ref(), &progress, &cancel)
+                }));
+            }
+            ui.label(format!("{} cards", selected.len()));
+        });
+        if let Some(message) = &self.message {
+            ui.label(message);
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use instant::Instant;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressUnit {
+    Bytes,
+    Items,
+}
+
+#[derive(Debug, Clone)]
+pub struct ProgressState {
+    pub stage: String,
+    pub unit: ProgressUnit,
+    pub done: u64,
+    pub total: Option<u64>,
+    pub started: Instant,
+}
+
+// a cheap handle that worker threads report into and the ui reads from every frame
+#[derive(Debug, Clone)]
+pub struct Progress {
+    state: Arc<Mutex<ProgressState>>,
+}
+
+impl Progress {
+    pub fn new(stage: &str, unit: ProgressUnit) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(ProgressState {
+                stage: stage.to_owned(),
+                unit,
+                done: 0,
+                total: None,
+                started: Instant::now(),
+            })),
+        }
+    }
+
+    pub fn start(&self, stage: &str, unit: ProgressUnit, total: Option<u64>) {
+        if let Ok(mut state) = self.state.lock() {
+            state.stage = stage.to_owned();
+            state.unit = unit;
+            state.done = 0;
+            state.total = total;
+            state.started = Instant::now();
+        }
+    }
+
+    pub fn advance(&self, amount: u64) {
+        if let Ok(mut state) = self.state.lock() {
+            state.done += amount;
+        }
+    }
+
+    pub fn add_total(&self, amount: u64) {
+        if let Ok(mut state) = self.state.lock() {
+            state.total = Some(state.total.unwrap_or(0) + amount);
+        }
+    }
+
+    pub fn snapshot(&self) -> ProgressState {
+        match self.state.lock() {
+            Ok(state) => state.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+}
+
+impl ProgressState {
+    pub fn fraction(&self) -> Option<f32> {
+        match self.total {
+            Some(total) if total > 0 => Some((self.done as f32 / total as f32).min(1.0)),
+            _ => None,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(self.total, Some(total) if self.done >= total)
+    }
+
+    pub fn eta(&self) -> Option<Duration> {
+        let total = self.total?;
+        let elapsed = self.started.elapsed().as_secs_f64();
+        if self.done == 0 || elapsed < 0.5 {
+            return None;
+        }
+        let rate = self.done as f64 / elapsed;
+        Some(Duration::from_secs_f64(total.saturating_sub(self.done) as f64 / rate))
+    }
+
+    pub fn describe(&self) -> String {
+        let amount = match (self.unit, self.total) {
+            (ProgressUnit::Bytes, Some(total)) => format!("{} / {}", format_bytes(self.done), format_bytes(total)),
+            (ProgressUnit::Bytes, None) => format_bytes(self.done),
+            (ProgressUnit::Items, Some(total)) => format!("{} / {}", self.done, total),
+            (ProgressUnit::Items, None) => self.done.to_string(),
+        };
+        if let Some(eta) = self.eta() {
+            format!("{}: {} ({}s left)", self.stage, amount, eta.as_secs())
+        } else {
+            format!("{}: {}", self.stage, amount)
+        }
+    }
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
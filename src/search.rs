@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+use wildmatch::WildMatch;
+
+use crate::yugioh::YugiohCard;
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct YugiohCardSearchCriteria {
+    pub string: String,
+}
+
+impl YugiohCardSearchCriteria {
+    pub fn new() -> Self {
+        Self { string: String::new() }
+    }
+}
+
+impl YugiohCardSearchCriteria {
+    pub fn matches(self, card: &YugiohCard) -> bool {
+        // check if a card matches the criteria
+        // for the time being just check if the name contains the string
+        match_wild(self.string.to_lowercase(), card.name.to_lowercase())
+    }
+}
+
+fn match_wild(wild: String, string: String) -> bool {
+    // if the string does not end with an !, add a wildcard to the end
+    let mut wild = wild;
+    if wild.ends_with('!') {
+        wild.pop();
+    } else {
+        wild.push('*');
+    }
+
+    if wild.starts_with('!') {
+        wild.remove(0);
+    } else {
+        wild.insert(0, '*');
+    }
+    // replace - in both strings with a space
+    let mut special_chars = vec!['-', '(', ')', '[', ']', '{', '}', '+', '.', '\\', '^', '$', '|', '"', '\'', '!'];
+    // if the wild string contains any of the special chars, remove them from the special chars list
+    for c in wild.chars() {
+        if special_chars.contains(&c) {
+            special_chars.retain(|&x| x != c);
+        }
+    }
+    let wild = clean_string(wild, special_chars.clone());
+    let string = clean_string(string, special_chars);
+    // println!("wild: {} | string: {}", wild, string);
+    WildMatch::new(&wild).matches(&string)
+}
+
+fn clean_string(string: String, special_chars: Vec<char>) -> String {
+    let mut string = string;
+    for char in special_chars.iter() {
+        string = string.replace(*char, "");
+    }
+    string
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SortingMode {
+    pub stype: SortingType,
+    pub order: SortOrder,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SortingType {
+    Name,
+    Id,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SortOrder {
+    Asc,
+    Dsc,
+}
+
+// indices into cards of every card matching the criteria, in the requested order
+pub fn search(cards: &[YugiohCard], criteria: &YugiohCardSearchCriteria, sorting: SortingMode) -> Vec<usize> {
+    let mut c: Vec<usize> = Vec::new();
+    for (i, card) in cards.iter().enumerate() {
+        if criteria.clone().matches(card) {
+            c.push(i);
+        }
+    }
+    let compare = match sorting.stype {
+        SortingType::Name => |a: &YugiohCard, b: &YugiohCard| a.name.cmp(&b.name),
+        SortingType::Id => |a: &YugiohCard, b: &YugiohCard| a.id.cmp(&b.id),
+    };
+    if sorting.order == SortOrder::Dsc {
+        c.sort_by(|a, b| compare(&cards[*b], &cards[*a]));
+    } else {
+        c.sort_by(|a, b| compare(&cards[*a], &cards[*b]));
+    }
+    c
+}
@@ -0,0 +1,88 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use ygodeckbuilderproelite::{diskcache::CacheLimits, http::HttpConfig, Format, NetworkPolicy, PriceVendor, SortOrder, SortingMode, SortingType};
+
+use crate::{images::DEFAULT_TEXTURE_BUDGET, store::Store};
+
+const SETTINGS_FILE: &str = "settings.json";
+// the key the settings are kept under in eframe's storage, which is local storage in the browser
+const STORAGE_KEY: &str = "settings";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ListDisplayMode {
+    Card,
+    ImageOnly,
+    Compact,
+}
+
+// everything the gui remembers between launches, fields missing from older files fall back to their defaults
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub ppp: f32,
+    pub list_display_mode: ListDisplayMode,
+    pub sorting: SortingMode,
+    pub search: String,
+    pub network_policy: NetworkPolicy,
+    pub format: Format,
+    pub price_vendor: PriceVendor,
+    // shown in front of prices, the card data itself has no currency
+    pub currency: String,
+    pub texture_budget_mib: usize,
+    pub prefetch_rows: usize,
+    pub large_only: bool,
+    pub cache_limits: CacheLimits,
+    // None is deck.ydk in the store
+    pub deck_path: Option<PathBuf>,
+    pub http: HttpConfig,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            ppp: 1.0,
+            list_display_mode: ListDisplayMode::ImageOnly,
+            sorting: SortingMode {
+                stype: SortingType::Name,
+                order: SortOrder::Asc,
+            },
+            search: String::new(),
+            network_policy: NetworkPolicy::default(),
+            format: Format::Tcg,
+            price_vendor: PriceVendor::Tcgplayer,
+            currency: "$".to_owned(),
+            texture_budget_mib: DEFAULT_TEXTURE_BUDGET / (1024 * 1024),
+            prefetch_rows: 2,
+            large_only: false,
+            cache_limits: CacheLimits::default(),
+            deck_path: None,
+            http: HttpConfig::default(),
+        }
+    }
+}
+
+impl Settings {
+    // the settings file wins over eframe's storage, a broken file is reported and replaced by the defaults
+    pub fn load(store: &Store, storage: Option<&dyn eframe::Storage>) -> Self {
+        let text = match store.read(Path::new(SETTINGS_FILE)).or_else(|| storage.and_then(|storage| storage.get_string(STORAGE_KEY))) {
+            Some(text) => text,
+            None => return Self::default(),
+        };
+        serde_json::from_str(&text).unwrap_or_else(|e| {
+            eprintln!("Failed to read settings from {}, using the defaults: {}", SETTINGS_FILE, e);
+            Self::default()
+        })
+    }
+
+    pub fn save(&self, store: &Store) -> Result<(), anyhow::Error> {
+        store.write(Path::new(SETTINGS_FILE), &serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn store(&self, storage: &mut dyn eframe::Storage) {
+        match serde_json::to_string(self) {
+            Ok(text) => storage.set_string(STORAGE_KEY, text),
+            Err(e) => eprintln!("Failed to store settings: {}", e),
+        }
+    }
+}
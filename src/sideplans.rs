@@ -0,0 +1,153 @@
+use std::path::Path;
+
+use eframe::egui;
+use ygodeckbuilderproelite::{deck::count_cards, siding, validate, DeckType, Format, SidePlan, YugiohCard, YugiohDeck};
+
+use crate::store::Store;
+
+// the window for the side deck plans of the open deck, they are saved in the .ydk along with the deck
+pub struct SidePlans {
+    selected: Option<usize>,
+    new_matchup: String,
+    message: Option<String>,
+}
+
+impl SidePlans {
+    pub fn new() -> Self {
+        Self {
+            selected: None,
+            new_matchup: String::new(),
+            message: None,
+        }
+    }
+
+    // true when a plan changed, which makes the deck unsaved
+    pub fn ui(&mut self, ui: &mut egui::Ui, deck: &mut YugiohDeck, cards: &[YugiohCard], format: Format, store: &Store, deck_path: &Path) -> bool {
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.new_matchup).hint_text("matchup").desired_width(160.));
+            let matchup = self.new_matchup.trim();
+            let exists = deck.side_plans.iter().any(|plan| plan.matchup.eq_ignore_ascii_case(matchup));
+            if ui.add_enabled(!matchup.is_empty() && !exists, egui::Button::new("Add plan")).clicked() {
+                deck.side_plans.push(SidePlan::new(matchup.to_owned()));
+                self.selected = Some(deck.side_plans.len() - 1);
+                self.new_matchup.clear();
+                changed = true;
+            }
+        });
+        ui.horizontal_wrapped(|ui| {
+            for (i, plan) in deck.side_plans.iter().enumerate() {
+                // plans with problems stand out so they get fixed before the event
+                let mut text = egui::RichText::new(&plan.matchup);
+                if !plan.issues(deck).is_empty() {
+                    text = text.color(egui::Color32::YELLOW);
+                }
+                if ui.selectable_label(self.selected == Some(i), text).clicked() {
+                    self.selected = Some(i);
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            if ui.add_enabled(!deck.side_plans.is_empty(), egui::Button::new("Copy guide")).clicked() {
+                ui.output().copied_text = guide(deck, cards, deck_path);
+            }
+            // a plain text file next to the deck, easy to print
+            if ui.add_enabled(!deck.side_plans.is_empty(), egui::Button::new("Save guide")).clicked() {
+                let mut path = deck_path.as_os_str().to_owned();
+                path.push(".siding.txt");
+                let path = std::path::PathBuf::from(path);
+                self.message = Some(match store.write(&path, &guide(deck, cards, deck_path)) {
+                    Ok(()) => format!("Saved to {}", path.display()),
+                    Err(e) => format!("Failed to save {}: {}", path.display(), e),
+                });
+            }
+            if let Some(message) = &self.message {
+                ui.label(message);
+            }
+        });
+        ui.separator();
+        let index = match self.selected.filter(|selected| *selected < deck.side_plans.len()) {
+            Some(index) => index,
+            None => {
+                ui.label("Add a plan for a matchup or pick one");
+                return changed;
+            }
+        };
+        let mut plan = deck.side_plans[index].clone();
+        let mut delete = false;
+        ui.horizontal(|ui| {
+            ui.label("Matchup");
+            ui.text_edit_singleline(&mut plan.matchup);
+            delete = ui.button("Delete plan").clicked();
+        });
+        ui.columns(2, |columns| {
+            let side = count_cards(&deck.side_deck);
+            let played: Vec<usize> = deck.main_deck.iter().chain(deck.extra_deck.iter()).copied().collect();
+            card_counts(&mut columns[0], "In from the side deck", &side, &mut plan.side_in, cards);
+            card_counts(&mut columns[1], "Out of the deck", &count_cards(&played), &mut plan.side_out, cards);
+        });
+        ui.label("Notes");
+        ui.add(egui::TextEdit::multiline(&mut plan.notes).desired_rows(2).desired_width(f32::INFINITY));
+        let sided = plan.apply(deck, cards);
+        let problems: Vec<String> = plan
+            .issues(deck)
+            .iter()
+            .map(|issue| issue.describe(cards))
+            .chain(validate(&sided, cards, format).iter().map(|issue| format!("After siding: {}", issue.describe(cards))))
+            .collect();
+        for problem in &problems {
+            ui.colored_label(egui::Color32::YELLOW, problem);
+        }
+        ui.collapsing("Deck after siding", |ui| {
+            egui::ScrollArea::vertical().max_height(240.).show(ui, |ui| {
+                for section in [DeckType::Main, DeckType::Extra, DeckType::Side] {
+                    let counts = count_cards(sided.section(section).map(Vec::as_slice).unwrap_or_default());
+                    ui.strong(format!("{} ({})", section.name(), counts.iter().map(|(_, count)| count).sum::<usize>()));
+                    for (card, count) in counts {
+                        ui.label(format!("{}x {}", count, cards[card].name));
+                    }
+                }
+            });
+        });
+        if delete {
+            deck.side_plans.remove(index);
+            self.selected = None;
+            return true;
+        }
+        if plan != deck.side_plans[index] {
+            deck.side_plans[index] = plan;
+            changed = true;
+        }
+        changed
+    }
+}
+
+// one row per card the deck has with how many copies the plan moves, never more than there are
+fn card_counts(ui: &mut egui::Ui, heading: &str, available: &[(usize, usize)], planned: &mut Vec<usize>, cards: &[YugiohCard]) {
+    ui.strong(format!("{} ({})", heading, planned.len()));
+    egui::ScrollArea::vertical().id_source(heading).max_height(240.).show(ui, |ui| {
+        egui::Grid::new(heading).num_columns(3).show(ui, |ui| {
+            for (card, count) in available {
+                let moved = planned.iter().filter(|c| *c == card).count();
+                ui.label(&cards[*card].name);
+                ui.label(format!("{}/{}", moved, count));
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(moved > 0, egui::Button::new("-")).clicked() {
+                        if let Some(position) = planned.iter().rposition(|c| c == card) {
+                            planned.remove(position);
+                        }
+                    }
+                    if ui.add_enabled(moved < *count, egui::Button::new("+")).clicked() {
+                        planned.push(*card);
+                    }
+                });
+                ui.end_row();
+            }
+        });
+    });
+}
+
+fn guide(deck: &YugiohDeck, cards: &[YugiohCard], deck_path: &Path) -> String {
+    let title = deck_path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+    siding::siding_guide(&title, deck, cards)
+}
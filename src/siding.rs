@@ -0,0 +1,261 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    deck::{self, count_cards, DeckType, YugiohDeck},
+    yugioh::YugiohCard,
+};
+
+// every plan is a "##plan {json}" line of its own in the .ydk
+pub const PLAN_TAG: &str = "plan";
+
+// what to change between games against one matchup, which side deck cards come in and which main or extra deck cards make room
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SidePlan {
+    pub matchup: String,
+    // card indices like in YugiohDeck, a card that comes in twice is listed twice
+    pub side_in: Vec<usize>,
+    pub side_out: Vec<usize>,
+    pub notes: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum PlanIssue {
+    // more copies come in than the side deck has
+    NotInSide { card: usize, wanted: usize, available: usize },
+    // more copies go out than the main and extra deck have
+    NotInDeck { card: usize, wanted: usize, available: usize },
+    // the side deck has to stay the same size, so every card that comes in needs one that goes out
+    Unbalanced { side_in: usize, side_out: usize },
+}
+
+impl PlanIssue {
+    pub fn describe(&self, cards: &[YugiohCard]) -> String {
+        match self {
+            PlanIssue::NotInSide { card, wanted, available } => format!("{} comes in {} times, the side deck has {}", cards[*card].name, wanted, available),
+            PlanIssue::NotInDeck { card, wanted, available } => format!("{} goes out {} times, the deck has {}", cards[*card].name, wanted, available),
+            PlanIssue::Unbalanced { side_in, side_out } => format!("{} cards come in but {} go out", side_in, side_out),
+        }
+    }
+}
+
+// how a plan is written to the .ydk, by passcode since card indices only mean something for one card list
+#[derive(Serialize, Deserialize)]
+struct StoredPlan {
+    matchup: String,
+    #[serde(rename = "in")]
+    side_in: Vec<u32>,
+    #[serde(rename = "out")]
+    side_out: Vec<u32>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    notes: String,
+}
+
+impl SidePlan {
+    pub fn new(matchup: String) -> Self {
+        Self { matchup, ..Self::default() }
+    }
+
+    pub fn issues(&self, deck: &YugiohDeck) -> Vec<PlanIssue> {
+        let mut issues = Vec::new();
+        for (card, wanted) in count_cards(&self.side_in) {
+            let available = deck.side_deck.iter().filter(|c| **c == card).count();
+            if wanted > available {
+                issues.push(PlanIssue::NotInSide { card, wanted, available });
+            }
+        }
+        for (card, wanted) in count_cards(&self.side_out) {
+            let available = deck.main_deck.iter().chain(deck.extra_deck.iter()).filter(|c| **c == card).count();
+            if wanted > available {
+                issues.push(PlanIssue::NotInDeck { card, wanted, available });
+            }
+        }
+        if self.side_in.len() != self.side_out.len() {
+            issues.push(PlanIssue::Unbalanced {
+                side_in: self.side_in.len(),
+                side_out: self.side_out.len(),
+            });
+        }
+        issues
+    }
+
+    // the deck as it is played after siding, cards the plan moves that the deck does not have are skipped
+    pub fn apply(&self, deck: &YugiohDeck, cards: &[YugiohCard]) -> YugiohDeck {
+        let mut sided = deck.clone();
+        for card in &self.side_out {
+            if sided.remove_card(*card, DeckType::Main) || sided.remove_card(*card, DeckType::Extra) {
+                sided.add_card(*card, DeckType::Side);
+            }
+        }
+        for card in &self.side_in {
+            if sided.remove_card(*card, DeckType::Side) {
+                sided.add_card(*card, if cards[*card].is_extra_deck() { DeckType::Extra } else { DeckType::Main });
+            }
+        }
+        sided
+    }
+
+    // the plan as a few lines of a siding guide
+    pub fn guide(&self, cards: &[YugiohCard]) -> String {
+        let list = |section: &[usize]| {
+            let counts = count_cards(section);
+            if counts.is_empty() {
+                return "nothing".to_owned();
+            }
+            counts.iter().map(|(card, count)| format!("{} {}", count, cards[*card].name)).collect::<Vec<_>>().join(", ")
+        };
+        let mut text = format!("vs {}\n  In:  {}\n  Out: {}\n", self.matchup, list(&self.side_in), list(&self.side_out));
+        for line in self.notes.lines() {
+            text.push_str(&format!("  {}\n", line));
+        }
+        text
+    }
+
+    pub fn to_comment(&self, cards: &[YugiohCard]) -> String {
+        let stored = StoredPlan {
+            matchup: self.matchup.clone(),
+            side_in: self.side_in.iter().map(|card| cards[*card].id).collect(),
+            side_out: self.side_out.iter().map(|card| cards[*card].id).collect(),
+            notes: self.notes.clone(),
+        };
+        deck::to_data_comment(PLAN_TAG, &stored)
+    }
+
+    // None if the line is not a plan or can not be read, cards missing from the card list are dropped from it
+    pub fn from_comment(line: &str, cards: &[YugiohCard]) -> Option<Self> {
+        let stored: StoredPlan = deck::from_data_comment(line, PLAN_TAG)?;
+        let find = |ids: Vec<u32>| ids.into_iter().filter_map(|id| deck::card_index(cards, id)).collect();
+        Some(Self {
+            matchup: stored.matchup,
+            side_in: find(stored.side_in),
+            side_out: find(stored.side_out),
+            notes: stored.notes,
+        })
+    }
+}
+
+// every plan of the deck, for printing and taking to an event
+pub fn siding_guide(title: &str, deck: &YugiohDeck, cards: &[YugiohCard]) -> String {
+    let mut text = format!("Siding guide for {}\n", title);
+    for plan in &deck.side_plans {
+        text.push('\n');
+        text.push_str(&plan.guide(cards));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testcards::{cards, deck, BLUE_EYES, DARK_MAGICIAN, POT_OF_GREED, ULTIMATE_DRAGON};
+
+    fn plan(side_in: &[usize], side_out: &[usize]) -> SidePlan {
+        SidePlan {
+            side_in: side_in.to_vec(),
+            side_out: side_out.to_vec(),
+            ..SidePlan::new("Mirror".to_owned())
+        }
+    }
+
+    #[test]
+    fn balanced_plan_has_no_issues() {
+        let deck = deck(&[BLUE_EYES, DARK_MAGICIAN], &[], &[POT_OF_GREED]);
+        assert!(plan(&[POT_OF_GREED], &[DARK_MAGICIAN]).issues(&deck).is_empty());
+    }
+
+    #[test]
+    fn more_copies_in_than_the_side_deck_has() {
+        let deck = deck(&[BLUE_EYES, DARK_MAGICIAN], &[], &[POT_OF_GREED]);
+        assert_eq!(
+            plan(&[POT_OF_GREED, POT_OF_GREED], &[BLUE_EYES, DARK_MAGICIAN]).issues(&deck),
+            vec![PlanIssue::NotInSide {
+                card: POT_OF_GREED,
+                wanted: 2,
+                available: 1
+            }]
+        );
+    }
+
+    #[test]
+    fn more_copies_out_than_the_deck_has() {
+        let deck = deck(&[BLUE_EYES], &[ULTIMATE_DRAGON], &[POT_OF_GREED]);
+        // the extra deck counts too
+        assert!(plan(&[POT_OF_GREED], &[ULTIMATE_DRAGON]).issues(&deck).is_empty());
+        assert_eq!(
+            plan(&[POT_OF_GREED], &[DARK_MAGICIAN]).issues(&deck),
+            vec![PlanIssue::NotInDeck {
+                card: DARK_MAGICIAN,
+                wanted: 1,
+                available: 0
+            }]
+        );
+    }
+
+    #[test]
+    fn unbalanced_plan() {
+        let deck = deck(&[BLUE_EYES, DARK_MAGICIAN], &[], &[POT_OF_GREED]);
+        assert_eq!(plan(&[POT_OF_GREED], &[]).issues(&deck), vec![PlanIssue::Unbalanced { side_in: 1, side_out: 0 }]);
+    }
+
+    #[test]
+    fn apply_keeps_section_sizes() {
+        let cards = cards();
+        let before = deck(&[BLUE_EYES, BLUE_EYES, DARK_MAGICIAN], &[], &[POT_OF_GREED, ULTIMATE_DRAGON]);
+        let sided = plan(&[POT_OF_GREED, ULTIMATE_DRAGON], &[DARK_MAGICIAN, BLUE_EYES]).apply(&before, &cards);
+        assert_eq!(sided.main_deck, vec![BLUE_EYES, POT_OF_GREED]);
+        // extra deck monsters go where they belong
+        assert_eq!(sided.extra_deck, vec![ULTIMATE_DRAGON]);
+        assert_eq!(sided.side_deck, vec![DARK_MAGICIAN, BLUE_EYES]);
+        assert_eq!(sided.side_deck.len(), before.side_deck.len());
+        assert_eq!(sided.main_deck.len() + sided.extra_deck.len(), before.main_deck.len() + before.extra_deck.len());
+        for card in [BLUE_EYES, DARK_MAGICIAN, POT_OF_GREED, ULTIMATE_DRAGON] {
+            assert_eq!(sided.count(card), before.count(card));
+        }
+    }
+
+    #[test]
+    fn apply_skips_cards_the_deck_does_not_have() {
+        let cards = cards();
+        let before = deck(&[BLUE_EYES], &[], &[POT_OF_GREED]);
+        let sided = plan(&[POT_OF_GREED, POT_OF_GREED], &[DARK_MAGICIAN]).apply(&before, &cards);
+        assert_eq!(sided.main_deck, vec![BLUE_EYES, POT_OF_GREED]);
+        assert!(sided.side_deck.is_empty());
+    }
+
+    #[test]
+    fn plans_survive_a_ydk_round_trip() {
+        let cards = cards();
+        let mut saved = deck(&[BLUE_EYES, DARK_MAGICIAN], &[], &[POT_OF_GREED]);
+        saved.side_plans.push(SidePlan {
+            notes: "go second\n\"keep\" the pot".to_owned(),
+            ..plan(&[POT_OF_GREED], &[DARK_MAGICIAN])
+        });
+        saved.side_plans.push(SidePlan::new("Going first, #main and !side".to_owned()));
+        let loaded = YugiohDeck::from_ydk(&saved.to_ydk(&cards), &cards);
+        assert_eq!(loaded, saved);
+    }
+
+    #[test]
+    fn unknown_and_broken_comment_lines_are_ignored() {
+        let cards = cards();
+        let text = "#created by someone else\n##notes {\"a\":1}\n##plan not json\n#main\n89631139\n#extra\n!side\n55144522\n";
+        let loaded = YugiohDeck::from_ydk(text, &cards);
+        assert!(loaded.side_plans.is_empty());
+        assert_eq!(loaded.main_deck, vec![BLUE_EYES]);
+        assert_eq!(loaded.side_deck, vec![POT_OF_GREED]);
+    }
+
+    #[test]
+    fn guide_lists_every_plan() {
+        let cards = cards();
+        let mut saved = deck(&[BLUE_EYES, DARK_MAGICIAN], &[], &[POT_OF_GREED]);
+        saved.side_plans.push(SidePlan {
+            notes: "go second".to_owned(),
+            ..plan(&[POT_OF_GREED], &[DARK_MAGICIAN])
+        });
+        saved.side_plans.push(SidePlan::new("Burn".to_owned()));
+        assert_eq!(
+            siding_guide("Blue-Eyes", &saved, &cards),
+            "Siding guide for Blue-Eyes\n\nvs Mirror\n  In:  1 Pot of Greed\n  Out: 1 Dark Magician\n  go second\n\nvs Burn\n  In:  nothing\n  Out: nothing\n"
+        );
+    }
+}
@@ -7,17 +7,6 @@ impl<T> Buffer<T> {
     pub fn new(size: usize) -> Self {
         Self { size, buffer: Vec::new() }
     }
-    pub fn get_index(&self) -> Option<usize> {
-        let i = self.buffer.iter().position(|x| x.is_none());
-
-        if let Some(i) = i {
-            Some(i)
-        } else if self.buffer.len() < self.size {
-            Some(self.buffer.len() - 1)
-        } else {
-            None
-        }
-    }
     pub fn try_add(&mut self, item: T) -> Result<usize, T> {
         let index = self.buffer.iter().position(|x| x.is_none());
         if let Some(index) = index {
@@ -44,6 +33,9 @@ impl<T> Buffer<T> {
             None
         }
     }
+    pub fn free(&self) -> usize {
+        self.size - self.buffer.iter().filter(|x| x.is_some()).count()
+    }
     #[allow(dead_code)]
     pub fn clear(&mut self) {
         self.buffer.clear();
@@ -0,0 +1,244 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::{io::Read, sync::Arc};
+use std::{path::PathBuf, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::http::HttpConfig;
+use crate::{
+    loader::LoadError,
+    progress::{Progress, ProgressUnit},
+    yugioh::ImageSize,
+};
+
+pub const YGOPRODECK_CARD_INFO_URL: &str = "https://db.ygoprodeck.com/api/v7/cardinfo.php";
+const STUB_ETAG: &str = "\"stub\"";
+
+// what the server told us identifies the version of the card database we have cached
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+pub enum DatabaseResponse {
+    Modified { data: Vec<u8>, validators: Validators },
+    // the cached copy the validators came from is still current
+    NotModified,
+}
+
+// where card data and card images come from, so the app can be pointed at a mirror, a local copy or a stub
+// sources block until they have an answer, so the browser, which can not block, fetches its data without them
+pub trait CardSource: Send + Sync {
+    fn name(&self) -> String;
+    // the raw cardinfo.php json
+    fn fetch_database(&self, progress: &Progress) -> Result<Vec<u8>, LoadError>;
+    // like fetch_database, but only sends the data if it changed since validators, sources that can not tell always send it
+    fn fetch_database_if_modified(&self, _validators: &Validators, progress: &Progress) -> Result<DatabaseResponse, LoadError> {
+        self.fetch_database(progress).map(|data| DatabaseResponse::Modified {
+            data,
+            validators: Validators::default(),
+        })
+    }
+    // the raw bytes of one card image, url is the one the database gave us for this image
+    fn fetch_image(&self, id: u32, size: ImageSize, url: &str) -> Result<Vec<u8>, anyhow::Error>;
+}
+
+// picks a source from YGO_CARD_SOURCE:
+//   unset or "ygoprodeck"  the public ygoprodeck api
+//   "http://..."           a mirror of cardinfo.php, images come from YGO_IMAGE_BASE_URL/<size>/<id>.jpg if set
+//   "local:<dir>"          <dir>/cards.json and <dir>/images/<size>/<id>.jpg
+//   "stub"                 a tiny built in database with placeholder images, for ci
+// http sources make their requests as configured by http, that fails when the proxy or a certificate is invalid
+#[cfg(not(target_arch = "wasm32"))]
+pub fn from_env(http: &HttpConfig) -> Result<Arc<dyn CardSource>, anyhow::Error> {
+    let source = std::env::var("YGO_CARD_SOURCE").unwrap_or_default();
+    let image_base_url = std::env::var("YGO_IMAGE_BASE_URL").ok();
+    Ok(if source == "stub" {
+        Arc::new(StubSource)
+    } else if let Some(dir) = source.strip_prefix("local:") {
+        Arc::new(LocalSource { dir: PathBuf::from(dir) })
+    } else if source.starts_with("http://") || source.starts_with("https://") {
+        Arc::new(HttpSource::new(source, image_base_url, http.client()?))
+    } else {
+        if !source.is_empty() && source != "ygoprodeck" {
+            eprintln!("Unknown card source {:?}, using ygoprodeck", source);
+        }
+        Arc::new(HttpSource::new(YGOPRODECK_CARD_INFO_URL.to_owned(), image_base_url, http.client()?))
+    })
+}
+
+// returned by fetch_image when the server answers 429, callers should hold off on that host for retry_after
+#[derive(Debug)]
+pub struct RateLimited {
+    pub retry_after: Option<Duration>,
+}
+
+impl std::fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.retry_after {
+            Some(retry_after) => write!(f, "Rate limited, retry after {} seconds", retry_after.as_secs()),
+            None => write!(f, "Rate limited"),
+        }
+    }
+}
+
+impl std::error::Error for RateLimited {}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub struct HttpSource {
+    pub database_url: String,
+    pub image_base_url: Option<String>,
+    // one client for every request so connections to the image host are kept alive and reused
+    client: reqwest::blocking::Client,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl HttpSource {
+    pub fn new(database_url: String, image_base_url: Option<String>, client: reqwest::blocking::Client) -> Self {
+        Self { database_url, image_base_url, client }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl CardSource for HttpSource {
+    fn name(&self) -> String {
+        self.database_url.clone()
+    }
+
+    fn fetch_database(&self, progress: &Progress) -> Result<Vec<u8>, LoadError> {
+        match self.fetch_database_if_modified(&Validators::default(), progress)? {
+            DatabaseResponse::Modified { data, .. } => Ok(data),
+            DatabaseResponse::NotModified => Err(LoadError::Status(reqwest::StatusCode::NOT_MODIFIED.as_u16())),
+        }
+    }
+
+    fn fetch_database_if_modified(&self, validators: &Validators, progress: &Progress) -> Result<DatabaseResponse, LoadError> {
+        let mut request = self.client.get(self.database_url.as_str());
+        if let Some(etag) = &validators.etag {
+            request = request.header(reqwest::header::IF_NONE_MATCH, etag.as_str());
+        }
+        if let Some(last_modified) = &validators.last_modified {
+            request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified.as_str());
+        }
+        let mut response = request.send().map_err(|e| LoadError::Network(e.to_string()))?;
+        if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+            return Ok(DatabaseResponse::NotModified);
+        }
+        if !response.status().is_success() {
+            return Err(LoadError::Status(response.status().as_u16()));
+        }
+        let header = |name| response.headers().get(name).and_then(|value: &reqwest::header::HeaderValue| value.to_str().ok()).map(str::to_owned);
+        let validators = Validators {
+            etag: header(reqwest::header::ETAG),
+            last_modified: header(reqwest::header::LAST_MODIFIED),
+        };
+        progress.start("Downloading card data", ProgressUnit::Bytes, response.content_length());
+        // read the body in chunks instead of with text() so the ui can show how far along we are
+        let mut raw = Vec::new();
+        let mut chunk = vec![0; 64 * 1024];
+        loop {
+            let read = response.read(&mut chunk).map_err(|e| LoadError::Network(e.to_string()))?;
+            if read == 0 {
+                break;
+            }
+            raw.extend_from_slice(&chunk[..read]);
+            progress.advance(read as u64);
+        }
+        Ok(DatabaseResponse::Modified { data: raw, validators })
+    }
+
+    fn fetch_image(&self, id: u32, size: ImageSize, url: &str) -> Result<Vec<u8>, anyhow::Error> {
+        let url = match &self.image_base_url {
+            Some(base) => format!("{}/{}/{}.jpg", base.trim_end_matches('/'), size, id),
+            None => url.to_owned(),
+        };
+        let response = self.client.get(url.as_str()).send()?;
+        if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            // only the delay-seconds form of Retry-After, the http-date form is treated as no hint
+            let retry_after = response
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse::<u64>().ok())
+                .map(Duration::from_secs);
+            return Err(RateLimited { retry_after }.into());
+        }
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!("Image request for {} answered with status {}", url, response.status()));
+        }
+        Ok(response.bytes()?.to_vec())
+    }
+}
+
+pub struct LocalSource {
+    pub dir: PathBuf,
+}
+
+impl CardSource for LocalSource {
+    fn name(&self) -> String {
+        format!("local:{}", self.dir.display())
+    }
+
+    fn fetch_database(&self, progress: &Progress) -> Result<Vec<u8>, LoadError> {
+        progress.start("Reading local card data", ProgressUnit::Bytes, None);
+        let data = std::fs::read(self.dir.join("cards.json")).map_err(|e| LoadError::Network(e.to_string()))?;
+        progress.advance(data.len() as u64);
+        Ok(data)
+    }
+
+    fn fetch_image(&self, id: u32, size: ImageSize, _url: &str) -> Result<Vec<u8>, anyhow::Error> {
+        Ok(std::fs::read(self.dir.join("images").join(size.as_str()).join(format!("{}.jpg", id)))?)
+    }
+}
+
+pub struct StubSource;
+
+const STUB_DATABASE: &str = r#"{"data":[
+{"id":89631139,"name":"Blue-Eyes White Dragon","type":"Normal Monster","desc":"This legendary dragon is a powerful engine of destruction.","race":"Dragon","archetype":"Blue-Eyes","atk":3000,"def":2500,"level":8,"attribute":"LIGHT",
+"card_images":[{"id":89631139,"image_url":"stub://large/89631139","image_url_small":"stub://small/89631139","image_url_cropped":"stub://cropped/89631139"}],
+"card_prices":[{"cardmarket_price":"0.02","tcgplayer_price":"0.08","ebay_price":"1.25","amazon_price":"0.50","coolstuffinc_price":"0.49"}]},
+{"id":46986414,"name":"Dark Magician","type":"Normal Monster","desc":"The ultimate wizard in terms of attack and defense.","race":"Spellcaster","archetype":"Dark Magician","atk":2500,"def":2100,"level":7,"attribute":"DARK",
+"card_images":[{"id":46986414,"image_url":"stub://large/46986414","image_url_small":"stub://small/46986414","image_url_cropped":"stub://cropped/46986414"}],
+"card_prices":[{"cardmarket_price":"0.02","tcgplayer_price":"0.09","ebay_price":"0.99","amazon_price":"0.25","coolstuffinc_price":"0.39"}]},
+{"id":55144522,"name":"Pot of Greed","type":"Spell Card","desc":"Draw 2 cards.","race":"Normal",
+"card_images":[{"id":55144522,"image_url":"stub://large/55144522","image_url_small":"stub://small/55144522","image_url_cropped":"stub://cropped/55144522"}],
+"card_prices":[{"cardmarket_price":"0.10","tcgplayer_price":"0.15","ebay_price":"0.99","amazon_price":"0.50","coolstuffinc_price":"0.49"}]}
+]}"#;
+
+impl CardSource for StubSource {
+    fn name(&self) -> String {
+        "stub".to_owned()
+    }
+
+    fn fetch_database(&self, progress: &Progress) -> Result<Vec<u8>, LoadError> {
+        progress.start("Loading stub card data", ProgressUnit::Bytes, Some(STUB_DATABASE.len() as u64));
+        progress.advance(STUB_DATABASE.len() as u64);
+        Ok(STUB_DATABASE.as_bytes().to_vec())
+    }
+
+    // the stub data never changes, so it behaves like a server that always has the same etag
+    fn fetch_database_if_modified(&self, validators: &Validators, progress: &Progress) -> Result<DatabaseResponse, LoadError> {
+        if validators.etag.as_deref() == Some(STUB_ETAG) {
+            return Ok(DatabaseResponse::NotModified);
+        }
+        Ok(DatabaseResponse::Modified {
+            data: self.fetch_database(progress)?,
+            validators: Validators {
+                etag: Some(STUB_ETAG.to_owned()),
+                last_modified: None,
+            },
+        })
+    }
+
+    fn fetch_image(&self, id: u32, size: ImageSize, _url: &str) -> Result<Vec<u8>, anyhow::Error> {
+        // a flat placeholder with the usual card proportions, tinted by id so different cards are distinguishable
+        let (width, height) = size.dimensions();
+        let tint = (id % 200) as u8;
+        let placeholder = image::RgbaImage::from_pixel(width, height, image::Rgba([54 + tint / 2, 54, 54 + (200 - tint) / 2, 255]));
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        image::DynamicImage::ImageRgba8(placeholder).write_to(&mut bytes, image::ImageOutputFormat::Png)?;
+        Ok(bytes.into_inner())
+    }
+}
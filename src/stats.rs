@@ -0,0 +1,111 @@
+use serde::Serialize;
+
+use crate::{
+    deck::{count_cards, YugiohDeck},
+    yugioh::{PriceVendor, YugiohCard},
+};
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SectionStats {
+    pub cards: usize,
+    pub monsters: usize,
+    pub spells: usize,
+    pub traps: usize,
+    pub price: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeckStats {
+    pub main: SectionStats,
+    pub extra: SectionStats,
+    pub side: SectionStats,
+    pub vendor: PriceVendor,
+    // every card in every section at the vendor's price
+    pub price: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PricedCard {
+    pub id: u32,
+    pub name: String,
+    pub count: usize,
+    pub price: f32,
+    pub total: f32,
+}
+
+pub fn deck_stats(deck: &YugiohDeck, cards: &[YugiohCard], vendor: PriceVendor) -> DeckStats {
+    let section = |section: &[usize]| {
+        let mut stats = SectionStats::default();
+        for card in section.iter().map(|card| &cards[*card]) {
+            stats.cards += 1;
+            if card.card_type.contains("Spell") {
+                stats.spells += 1;
+            } else if card.card_type.contains("Trap") {
+                stats.traps += 1;
+            } else {
+                stats.monsters += 1;
+            }
+            stats.price += card.card_prices.get(vendor);
+        }
+        stats
+    };
+    let main = section(&deck.main_deck);
+    let extra = section(&deck.extra_deck);
+    let side = section(&deck.side_deck);
+    let price = main.price + extra.price + side.price;
+    DeckStats { main, extra, side, vendor, price }
+}
+
+// one line per distinct card with the price of a single copy and of all copies in the deck
+pub fn price_list(deck: &YugiohDeck, cards: &[YugiohCard], vendor: PriceVendor) -> Vec<PricedCard> {
+    let all: Vec<usize> = deck.main_deck.iter().chain(deck.extra_deck.iter()).chain(deck.side_deck.iter()).copied().collect();
+    count_cards(&all)
+        .into_iter()
+        .map(|(card, count)| {
+            let card = &cards[card];
+            let price = card.card_prices.get(vendor);
+            PricedCard {
+                id: card.id,
+                name: card.name.clone(),
+                count,
+                price,
+                total: price * count as f32,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testcards::{cards, deck, BLUE_EYES, DARK_MAGICIAN, MIRROR_FORCE, POT_OF_GREED, ULTIMATE_DRAGON};
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 0.001
+    }
+
+    #[test]
+    fn stats_per_section() {
+        let cards = cards();
+        let deck = deck(&[BLUE_EYES, BLUE_EYES, POT_OF_GREED, MIRROR_FORCE], &[ULTIMATE_DRAGON], &[MIRROR_FORCE]);
+        let stats = deck_stats(&deck, &cards, PriceVendor::Tcgplayer);
+        assert_eq!((stats.main.cards, stats.main.monsters, stats.main.spells, stats.main.traps), (4, 2, 1, 1));
+        assert_eq!((stats.extra.cards, stats.extra.monsters), (1, 1));
+        assert_eq!((stats.side.cards, stats.side.traps), (1, 1));
+        assert!(close(stats.main.price, 2.85));
+        assert!(close(stats.price, 2.85 + 2.00 + 0.75));
+    }
+
+    #[test]
+    fn price_list_counts_copies_across_sections() {
+        let cards = cards();
+        let deck = deck(&[DARK_MAGICIAN, BLUE_EYES, DARK_MAGICIAN], &[], &[DARK_MAGICIAN]);
+        let prices = price_list(&deck, &cards, PriceVendor::Cardmarket);
+        assert_eq!(
+            prices.iter().map(|card| (card.name.as_str(), card.count)).collect::<Vec<_>>(),
+            [("Dark Magician", 3), ("Blue-Eyes White Dragon", 1)]
+        );
+        assert!(close(prices[0].price, 0.50));
+        assert!(close(prices[0].total, 1.50));
+    }
+}
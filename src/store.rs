@@ -0,0 +1,143 @@
+use std::path::{Path, PathBuf};
+
+// where the gui keeps its own files, the settings and decks
+// natively that is the appdata directory, in the browser there is no file system and they go to local storage instead
+pub struct Store {
+    #[cfg(not(target_arch = "wasm32"))]
+    dir: PathBuf,
+    // None when the browser has local storage disabled, nothing is remembered then
+    #[cfg(target_arch = "wasm32")]
+    local: Option<web_sys::Storage>,
+}
+
+#[cfg(target_arch = "wasm32")]
+const KEY_PREFIX: &str = "ygodeckbuilderproelite/";
+
+impl Store {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn new() -> Self {
+        Self {
+            local: web_sys::window().and_then(|window| window.local_storage().ok().flatten()),
+        }
+    }
+
+    // where a file called name is kept by default, relative paths are read and written relative to the store as well
+    pub fn path(&self, name: &str) -> PathBuf {
+        #[cfg(not(target_arch = "wasm32"))]
+        return self.dir.join(name);
+        #[cfg(target_arch = "wasm32")]
+        return PathBuf::from(name);
+    }
+
+    // the same path however it was written, for keeping things per file
+    // a file that does not exist yet can not be resolved, its directory is resolved instead
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn canonical(&self, path: &Path) -> PathBuf {
+        let path = self.dir.join(path);
+        if let Ok(canonical) = std::fs::canonicalize(&path) {
+            return canonical;
+        }
+        match (path.parent().and_then(|parent| std::fs::canonicalize(parent).ok()), path.file_name()) {
+            (Some(parent), Some(name)) => parent.join(name),
+            _ => path,
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn read(&self, path: &Path) -> Option<String> {
+        std::fs::read_to_string(self.dir.join(path)).ok()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn write(&self, path: &Path, text: &str) -> Result<(), anyhow::Error> {
+        let path = self.dir.join(path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // written under another name first so a crash halfway through never leaves a truncated file behind
+        let mut part = path.clone().into_os_string();
+        part.push(".part");
+        std::fs::write(&part, text)?;
+        std::fs::rename(part, path)?;
+        Ok(())
+    }
+
+    // the files directly in dir, in no particular order
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn list(&self, dir: &Path) -> Vec<PathBuf> {
+        match std::fs::read_dir(self.dir.join(dir)) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.file_type().map(|file_type| file_type.is_file()).unwrap_or(false))
+                .map(|entry| dir.join(entry.file_name()))
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn remove(&self, path: &Path) -> Result<(), anyhow::Error> {
+        Ok(std::fs::remove_file(self.dir.join(path))?)
+    }
+
+    // local storage keys are plain text, there is nothing to resolve
+    #[cfg(target_arch = "wasm32")]
+    pub fn canonical(&self, path: &Path) -> PathBuf {
+        path.to_owned()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn read(&self, path: &Path) -> Option<String> {
+        self.local.as_ref()?.get_item(&key(path)).ok().flatten()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn write(&self, path: &Path, text: &str) -> Result<(), anyhow::Error> {
+        let local = self.local.as_ref().ok_or_else(|| anyhow::anyhow!("Local storage is not available"))?;
+        // the usual cause is the storage quota, the browser only tells us through a js exception
+        local
+            .set_item(&key(path), text)
+            .map_err(|e| anyhow::anyhow!("Local storage refused to store {}: {:?}", path.display(), e))
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn list(&self, dir: &Path) -> Vec<PathBuf> {
+        let local = match &self.local {
+            Some(local) => local,
+            None => return Vec::new(),
+        };
+        let prefix = format!("{}/", key(dir));
+        (0..local.length().unwrap_or(0))
+            .filter_map(|i| local.key(i).ok().flatten())
+            .filter_map(|key| Some(key.strip_prefix(&prefix)?.to_owned()))
+            .filter(|name| !name.contains('/'))
+            .map(|name| dir.join(name))
+            .collect()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn remove(&self, path: &Path) -> Result<(), anyhow::Error> {
+        let local = self.local.as_ref().ok_or_else(|| anyhow::anyhow!("Local storage is not available"))?;
+        local
+            .remove_item(&key(path))
+            .map_err(|e| anyhow::anyhow!("Local storage refused to remove {}: {:?}", path.display(), e))
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn key(path: &Path) -> String {
+    format!("{}{}", KEY_PREFIX, path.display())
+}
+
+// seconds since the unix epoch, the browser has no system clock std can read
+pub fn unix_time() -> u64 {
+    #[cfg(not(target_arch = "wasm32"))]
+    return std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|since| since.as_secs()).unwrap_or(0);
+    #[cfg(target_arch = "wasm32")]
+    return (js_sys::Date::now() / 1000.) as u64;
+}
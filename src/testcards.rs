@@ -0,0 +1,75 @@
+// a handful of cards for the unit tests, made from api data the same way the loader makes them
+use crate::{
+    deck::YugiohDeck,
+    yugioh::{RawBanlistInfo, RawCardImage, RawCardPrice, RawYugiohCard, YugiohCard},
+};
+
+// indices into cards()
+pub const BLUE_EYES: usize = 0;
+pub const DARK_MAGICIAN: usize = 1;
+// forbidden in tcg
+pub const POT_OF_GREED: usize = 2;
+// a fusion monster, it belongs in the extra deck
+pub const ULTIMATE_DRAGON: usize = 3;
+// names that start with a number, for text lists like "3 Name"
+pub const COLORED_FISH: usize = 4;
+pub const LACOODA: usize = 5;
+pub const MIRROR_FORCE: usize = 6;
+
+pub fn cards() -> Vec<YugiohCard> {
+    [
+        (89631139, "Blue-Eyes White Dragon", "Normal Monster", None, "1.00"),
+        (46986414, "Dark Magician", "Normal Monster", None, "0.50"),
+        (55144522, "Pot of Greed", "Spell Card", Some("Forbidden"), "0.10"),
+        (23995346, "Blue-Eyes Ultimate Dragon", "Fusion Monster", None, "2.00"),
+        (23771716, "7 Colored Fish", "Normal Monster", None, "0.25"),
+        (86988864, "3-Hump Lacooda", "Effect Monster", None, "0.05"),
+        (44095762, "Mirror Force", "Trap Card", None, "0.75"),
+    ]
+    .into_iter()
+    .map(|(id, name, card_type, ban, price): (u32, &str, &str, Option<&str>, &str)| {
+        YugiohCard::from_raw(RawYugiohCard {
+            id,
+            name: name.to_owned(),
+            card_type: card_type.to_owned(),
+            desc: String::new(),
+            race: String::new(),
+            archetype: None,
+            card_sets: None,
+            card_images: vec![RawCardImage {
+                id,
+                image_url: format!("stub://large/{}", id),
+                image_url_small: format!("stub://small/{}", id),
+                image_url_cropped: None,
+            }],
+            card_prices: vec![RawCardPrice {
+                cardmarket_price: price.to_owned(),
+                tcgplayer_price: price.to_owned(),
+                ebay_price: price.to_owned(),
+                amazon_price: price.to_owned(),
+                coolstuffinc_price: price.to_owned(),
+            }],
+            banlist_info: ban.map(|ban| RawBanlistInfo {
+                ban_tcg: Some(ban.to_owned()),
+                ban_ocg: None,
+                ban_goat: None,
+            }),
+            atk: None,
+            def: None,
+            level: None,
+            attribute: None,
+            linkval: None,
+            scale: None,
+        })
+    })
+    .collect()
+}
+
+// a deck made of indices into cards()
+pub fn deck(main: &[usize], extra: &[usize], side: &[usize]) -> YugiohDeck {
+    let mut deck = YugiohDeck::new(true);
+    deck.main_deck = main.to_vec();
+    deck.extra_deck = extra.to_vec();
+    deck.side_deck = side.to_vec();
+    deck
+}
@@ -0,0 +1,184 @@
+use eframe::egui::{self, Color32, Rect, Response, Sense, Ui, Vec2, Widget};
+use ygodeckbuilderproelite::{DeckType, ImageSize, NetworkPolicy, YugiohCard};
+
+use crate::images::{CardImages, ImageState};
+
+pub const ASPECT_RATIO: f32 = 2.25 / 3.25;
+pub const CARD_ROUNDING: f32 = 1.0;
+const TILE_BACKGROUND: Color32 = Color32::from_rgb(54, 54, 54);
+const ROW_BACKGROUND: Color32 = Color32::from_rgb(64, 64, 64);
+// the preview never grows past the size of the large image itself
+const PREVIEW_MAX_HEIGHT: f32 = 614.0;
+const PREVIEW_MARGIN: f32 = 8.0;
+
+// the outline or text color for cards that are already in the deck
+pub fn deck_color(deck_type: DeckType) -> Option<Color32> {
+    match deck_type {
+        DeckType::Main => Some(Color32::from_rgb(255, 255, 0)),
+        DeckType::Side => Some(Color32::from_rgb(0, 255, 255)),
+        DeckType::Extra => Some(Color32::from_rgb(255, 0, 255)),
+        DeckType::None => None,
+    }
+}
+
+// a failed image is retried when clicked, and that click is not passed on to whoever owns the widget
+fn retry_on_click(images: &mut CardImages, card: &YugiohCard, size: ImageSize, state: ImageState, response: &mut Response) {
+    if state == ImageState::Failed && response.clicked() {
+        images.retry(card.card_image.get(size));
+        response.clicked[egui::PointerButton::Primary as usize] = false;
+    }
+}
+
+// one card image in a frame, for grids of cards
+pub struct CardTile<'a> {
+    images: &'a mut CardImages,
+    card: &'a YugiohCard,
+    image_size: ImageSize,
+    size: Vec2,
+    outline: Option<Color32>,
+    policy: NetworkPolicy,
+}
+
+impl<'a> CardTile<'a> {
+    pub fn new(images: &'a mut CardImages, card: &'a YugiohCard, image_size: ImageSize, size: Vec2) -> Self {
+        Self {
+            images,
+            card,
+            image_size,
+            size,
+            outline: None,
+            policy: NetworkPolicy::default(),
+        }
+    }
+
+    pub fn outline(mut self, outline: Option<Color32>) -> Self {
+        self.outline = outline;
+        self
+    }
+
+    pub fn policy(mut self, policy: NetworkPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+}
+
+impl Widget for CardTile<'_> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let (rect, mut response) = ui.allocate_exact_size(self.size, Sense::click());
+        if !ui.is_rect_visible(rect) {
+            return response;
+        }
+        ui.painter().rect(rect, CARD_ROUNDING, TILE_BACKGROUND, egui::Stroke::new(1., ROW_BACKGROUND));
+        let state = self.images.paint(ui.painter(), rect, &self.card.card_image, self.image_size, self.policy);
+        retry_on_click(self.images, self.card, self.image_size, state, &mut response);
+        if let Some(outline) = self.outline {
+            ui.painter().rect_stroke(rect, CARD_ROUNDING, egui::Stroke::new(1., outline));
+        }
+        response
+    }
+}
+
+// a full width row with the card image on the left and its name next to it, for lists
+// rows tall enough for more than one line also show the type and race
+pub struct CardRow<'a> {
+    images: &'a mut CardImages,
+    card: &'a YugiohCard,
+    image_size: ImageSize,
+    height: f32,
+    count: Option<usize>,
+    text_color: Option<Color32>,
+    policy: NetworkPolicy,
+}
+
+impl<'a> CardRow<'a> {
+    pub fn new(images: &'a mut CardImages, card: &'a YugiohCard, image_size: ImageSize, height: f32) -> Self {
+        Self {
+            images,
+            card,
+            image_size,
+            height,
+            count: None,
+            text_color: None,
+            policy: NetworkPolicy::default(),
+        }
+    }
+
+    // shown in front of the name, for deck lists
+    pub fn count(mut self, count: usize) -> Self {
+        self.count = Some(count);
+        self
+    }
+
+    pub fn text_color(mut self, text_color: Option<Color32>) -> Self {
+        self.text_color = text_color;
+        self
+    }
+
+    pub fn policy(mut self, policy: NetworkPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+}
+
+impl Widget for CardRow<'_> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let (rect, mut response) = ui.allocate_exact_size(Vec2::new(ui.available_width(), self.height), Sense::click());
+        if !ui.is_rect_visible(rect) {
+            return response;
+        }
+        let painter = ui.painter();
+        if response.hovered() {
+            painter.rect_filled(rect, CARD_ROUNDING, ROW_BACKGROUND);
+        }
+        // cropped artwork is square, everything else has the shape of a card
+        let image_width = if self.image_size == ImageSize::Cropped { self.height } else { self.height * ASPECT_RATIO };
+        let image_rect = Rect::from_min_size(rect.min, Vec2::new(image_width, self.height)).shrink(1.);
+        painter.rect_filled(image_rect, CARD_ROUNDING, TILE_BACKGROUND);
+        let state = self.images.paint(painter, image_rect, &self.card.card_image, self.image_size, self.policy);
+        retry_on_click(self.images, self.card, self.image_size, state, &mut response);
+
+        let name = match self.count {
+            Some(count) => format!("{}x {}", count, self.card.name),
+            None => self.card.name.clone(),
+        };
+        let color = self.text_color.unwrap_or_else(|| ui.visuals().text_color());
+        let font = egui::FontId::default();
+        let line_height = ui.fonts().row_height(&font);
+        let text_x = image_rect.max.x + 6.;
+        let painter = ui.painter_at(rect);
+        if self.height < line_height * 3. {
+            painter.text(egui::Pos2::new(text_x, rect.center().y), egui::Align2::LEFT_CENTER, name, font, color);
+        } else {
+            let lines = [name, self.card.card_type.clone(), self.card.race.clone()];
+            for (i, line) in lines.into_iter().enumerate() {
+                let color = if i == 0 { color } else { ui.visuals().weak_text_color() };
+                painter.text(egui::Pos2::new(text_x, rect.min.y + 4. + line_height * i as f32), egui::Align2::LEFT_TOP, line, font.clone(), color);
+            }
+        }
+        response
+    }
+}
+
+// the large image of the hovered card, drawn above everything else beside the tile it belongs to
+// it goes on whichever side of the tile has more room and is shrunk and moved as needed to stay on screen
+pub fn show_preview(ctx: &egui::Context, images: &mut CardImages, card: &YugiohCard, anchor: Rect, policy: NetworkPolicy) {
+    let screen = ctx.input().screen_rect().shrink(PREVIEW_MARGIN);
+    let room_left = anchor.min.x - PREVIEW_MARGIN - screen.min.x;
+    let room_right = screen.max.x - anchor.max.x - PREVIEW_MARGIN;
+    let mut height = screen.height().min(PREVIEW_MAX_HEIGHT);
+    let width = (height * ASPECT_RATIO).min(room_left.max(room_right)).max(0.);
+    height = height.min(width / ASPECT_RATIO);
+    if width < 1. || height < 1. {
+        return;
+    }
+    let x = if room_right >= width || room_right >= room_left {
+        anchor.max.x + PREVIEW_MARGIN
+    } else {
+        anchor.min.x - PREVIEW_MARGIN - width
+    };
+    let y = (anchor.center().y - height / 2.).clamp(screen.min.y, (screen.max.y - height).max(screen.min.y));
+    let rect = Rect::from_min_size(egui::Pos2::new(x, y), Vec2::new(width, height));
+    let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Tooltip, egui::Id::new("card preview")));
+    painter.rect_filled(rect, CARD_ROUNDING, TILE_BACKGROUND);
+    images.paint(&painter, rect, &card.card_image, ImageSize::Large, policy);
+}
@@ -0,0 +1,145 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    deck::{DeckType, YugiohDeck},
+    yugioh::{BanStatus, YugiohCard},
+};
+
+pub const MAIN_DECK_MIN: usize = 40;
+pub const MAIN_DECK_MAX: usize = 60;
+pub const EXTRA_DECK_MAX: usize = 15;
+pub const SIDE_DECK_MAX: usize = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Format {
+    Tcg,
+    Ocg,
+    Goat,
+}
+
+impl Format {
+    pub const ALL: [Format; 3] = [Format::Tcg, Format::Ocg, Format::Goat];
+
+    pub fn ban_status(&self, card: &YugiohCard) -> BanStatus {
+        match self {
+            Format::Tcg => card.banlist.tcg,
+            Format::Ocg => card.banlist.ocg,
+            Format::Goat => card.banlist.goat,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::Tcg => "TCG",
+            Format::Ocg => "OCG",
+            Format::Goat => "GOAT",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum DeckIssue {
+    MainDeckSize(usize),
+    ExtraDeckSize(usize),
+    SideDeckSize(usize),
+    // card is an index into the card list, same as in YugiohDeck
+    TooManyCopies { card: usize, count: usize, max: usize },
+    WrongSection { card: usize, section: DeckType },
+}
+
+impl DeckIssue {
+    pub fn describe(&self, cards: &[YugiohCard]) -> String {
+        match self {
+            DeckIssue::MainDeckSize(size) => format!("Main deck has {} cards, it needs between {} and {}", size, MAIN_DECK_MIN, MAIN_DECK_MAX),
+            DeckIssue::ExtraDeckSize(size) => format!("Extra deck has {} cards, it can have at most {}", size, EXTRA_DECK_MAX),
+            DeckIssue::SideDeckSize(size) => format!("Side deck has {} cards, it can have at most {}", size, SIDE_DECK_MAX),
+            DeckIssue::TooManyCopies { card, count, max } => format!("{} copies of {}, at most {} allowed", count, cards[*card].name, max),
+            DeckIssue::WrongSection { card, section } => format!("{} does not belong in the {:?} deck", cards[*card].name, section),
+        }
+    }
+}
+
+pub fn validate(deck: &YugiohDeck, cards: &[YugiohCard], format: Format) -> Vec<DeckIssue> {
+    let mut issues = Vec::new();
+    if deck.main_deck.len() < MAIN_DECK_MIN || deck.main_deck.len() > MAIN_DECK_MAX {
+        issues.push(DeckIssue::MainDeckSize(deck.main_deck.len()));
+    }
+    if deck.extra_deck.len() > EXTRA_DECK_MAX {
+        issues.push(DeckIssue::ExtraDeckSize(deck.extra_deck.len()));
+    }
+    if deck.side_deck.len() > SIDE_DECK_MAX {
+        issues.push(DeckIssue::SideDeckSize(deck.side_deck.len()));
+    }
+    let mut checked = Vec::new();
+    for card in deck.main_deck.iter().chain(deck.extra_deck.iter()).chain(deck.side_deck.iter()) {
+        if checked.contains(card) {
+            continue;
+        }
+        checked.push(*card);
+        let count = deck.count(*card);
+        let max = format.ban_status(&cards[*card]).max_copies();
+        if count > max {
+            issues.push(DeckIssue::TooManyCopies { card: *card, count, max });
+        }
+    }
+    for card in deck.main_deck.iter().filter(|card| cards[**card].is_extra_deck()) {
+        issues.push(DeckIssue::WrongSection { card: *card, section: DeckType::Main });
+    }
+    for card in deck.extra_deck.iter().filter(|card| !cards[**card].is_extra_deck()) {
+        issues.push(DeckIssue::WrongSection {
+            card: *card,
+            section: DeckType::Extra,
+        });
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testcards::{cards, deck, BLUE_EYES, DARK_MAGICIAN, POT_OF_GREED, ULTIMATE_DRAGON};
+
+    #[test]
+    fn section_sizes() {
+        let cards = cards();
+        assert_eq!(validate(&deck(&[BLUE_EYES; 3], &[], &[]), &cards, Format::Tcg), vec![DeckIssue::MainDeckSize(3)]);
+        // these break the copy limits as well, the size issues come first
+        let oversized = validate(&deck(&[BLUE_EYES; 61], &[ULTIMATE_DRAGON; 16], &[DARK_MAGICIAN; 16]), &cards, Format::Tcg);
+        assert_eq!(oversized[..3], [DeckIssue::MainDeckSize(61), DeckIssue::ExtraDeckSize(16), DeckIssue::SideDeckSize(16)]);
+    }
+
+    #[test]
+    fn copies_are_counted_across_sections() {
+        let cards = cards();
+        let issues = validate(&deck(&[BLUE_EYES, BLUE_EYES, DARK_MAGICIAN], &[], &[BLUE_EYES, BLUE_EYES]), &cards, Format::Tcg);
+        assert_eq!(issues, vec![DeckIssue::MainDeckSize(3), DeckIssue::TooManyCopies { card: BLUE_EYES, count: 4, max: 3 }]);
+    }
+
+    #[test]
+    fn banlist_depends_on_the_format() {
+        let cards = cards();
+        let with_pot = deck(&[POT_OF_GREED], &[], &[]);
+        assert!(validate(&with_pot, &cards, Format::Tcg).contains(&DeckIssue::TooManyCopies { card: POT_OF_GREED, count: 1, max: 0 }));
+        assert_eq!(validate(&with_pot, &cards, Format::Ocg), vec![DeckIssue::MainDeckSize(1)]);
+    }
+
+    #[test]
+    fn cards_in_the_wrong_section() {
+        let cards = cards();
+        let issues = validate(&deck(&[ULTIMATE_DRAGON], &[DARK_MAGICIAN], &[ULTIMATE_DRAGON]), &cards, Format::Tcg);
+        assert_eq!(
+            issues,
+            vec![
+                DeckIssue::MainDeckSize(1),
+                DeckIssue::WrongSection {
+                    card: ULTIMATE_DRAGON,
+                    section: DeckType::Main
+                },
+                DeckIssue::WrongSection {
+                    card: DARK_MAGICIAN,
+                    section: DeckType::Extra
+                },
+            ]
+        );
+    }
+}
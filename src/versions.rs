@@ -0,0 +1,60 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use ygodeckbuilderproelite::{diskcache, hash};
+
+use crate::store::{self, Store};
+
+// past this many the oldest versions of a deck are deleted
+pub const MAX_VERSIONS: usize = 100;
+
+// a copy of a deck as it was saved at one point, versions/<deck file name>-<path hash>/<unix seconds>.ydk in the store
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+    pub path: PathBuf,
+    pub saved: u64,
+}
+
+impl Version {
+    pub fn describe(&self) -> String {
+        format!("Saved {} ago", diskcache::format_age(Duration::from_secs(store::unix_time().saturating_sub(self.saved))))
+    }
+}
+
+// decks with the same file name in different folders are told apart by a hash of the whole path,
+// the file name is only there to make the directory easy to find by hand
+fn dir(store: &Store, deck_path: &Path) -> PathBuf {
+    let path = store.canonical(deck_path);
+    let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_else(|| "deck".to_owned());
+    store.path("versions").join(format!("{}-{:016x}", name, hash::stable_hash(path.to_string_lossy().as_bytes())))
+}
+
+// every saved version of the deck at deck_path, newest first
+pub fn list(store: &Store, deck_path: &Path) -> Vec<Version> {
+    let mut versions: Vec<Version> = store
+        .list(&dir(store, deck_path))
+        .into_iter()
+        .filter_map(|path| {
+            let saved = path.file_stem()?.to_str()?.parse().ok()?;
+            Some(Version { path, saved })
+        })
+        .collect();
+    versions.sort_unstable_by_key(|version| std::cmp::Reverse(version.saved));
+    versions
+}
+
+// keeps text as a new version unless it is the same as the newest one, then drops whatever is past MAX_VERSIONS
+pub fn snapshot(store: &Store, deck_path: &Path, text: &str) -> Result<(), anyhow::Error> {
+    let versions = list(store, deck_path);
+    if versions.first().and_then(|newest| store.read(&newest.path)).as_deref() == Some(text) {
+        return Ok(());
+    }
+    // two saves within the same second overwrite each other, the later one is what was saved anyway
+    store.write(&dir(store, deck_path).join(format!("{}.ydk", store::unix_time())), text)?;
+    for old in versions.iter().skip(MAX_VERSIONS - 1) {
+        store.remove(&old.path)?;
+    }
+    Ok(())
+}
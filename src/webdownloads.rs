@@ -0,0 +1,64 @@
+use std::{cell::Cell, collections::HashSet, rc::Rc};
+
+use egui_extras::RetainedImage;
+use poll_promise::Promise;
+use ygodeckbuilderproelite::{CardImage, ImageSize, NetworkPolicy, YugiohImage};
+
+use crate::images::ImageKey;
+
+type ImageResult = Result<RetainedImage, anyhow::Error>;
+
+// the browser version of the image downloader, the browser limits connections per host and caches images on its own
+// so there is no worker pool and no disk cache here, every request is a fetch that starts right away
+pub struct Downloader {
+    in_flight: Rc<Cell<usize>>,
+}
+
+impl Downloader {
+    pub fn new() -> Self {
+        Self { in_flight: Rc::new(Cell::new(0)) }
+    }
+
+    // small images can not be made from large ones without a disk cache to keep them in
+    pub fn large_only(&self) -> bool {
+        false
+    }
+
+    pub fn request(&self, card_image: &CardImage, size: ImageSize, policy: NetworkPolicy) -> Promise<ImageResult> {
+        self.fetch(card_image.get(size), policy)
+    }
+
+    pub fn prefetch(&self, card_image: &CardImage, size: ImageSize, policy: NetworkPolicy) -> Promise<ImageResult> {
+        self.fetch(card_image.get(size), policy)
+    }
+
+    fn fetch(&self, image: &YugiohImage, policy: NetworkPolicy) -> Promise<ImageResult> {
+        if !policy.allows_network() {
+            return Promise::from_ready(Err(anyhow::anyhow!("{} image {} can not be loaded with the network disabled", image.size, image.id)));
+        }
+        let image = image.clone();
+        let in_flight = self.in_flight.clone();
+        in_flight.set(in_flight.get() + 1);
+        Promise::spawn_local(async move {
+            let result = download(&image).await;
+            in_flight.set(in_flight.get() - 1);
+            result
+        })
+    }
+
+    // a fetch the browser already started can not be taken back, so there is nothing to cancel
+    pub fn retain(&self, _visible: &HashSet<ImageKey>, _prefetched: &HashSet<ImageKey>) {}
+
+    pub fn queued(&self) -> usize {
+        self.in_flight.get()
+    }
+}
+
+async fn download(image: &YugiohImage) -> ImageResult {
+    let response = reqwest::get(image.url.as_str()).await?;
+    if !response.status().is_success() {
+        return Err(anyhow::anyhow!("Image request for {} answered with status {}", image.url, response.status()));
+    }
+    let bytes = response.bytes().await?;
+    RetainedImage::from_image_bytes(format!("{}:{}", image.size, image.id), &bytes[..]).map_err(|e| anyhow::anyhow!("Failed to load image bytes: {}", e))
+}
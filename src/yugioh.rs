@@ -1,14 +1,4 @@
-use std::{io::BufRead, path::PathBuf};
-
-use anyhow::anyhow;
-use eframe::epaint::TextureId;
-
-use egui_extras::RetainedImage;
-use poll_promise::Promise;
-use serde::Deserialize;
-use wildmatch::WildMatch;
-
-use crate::sizedbuffer::Buffer;
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct YugiohCards {
@@ -27,6 +17,13 @@ pub struct RawYugiohCard {
     pub card_sets: Option<Vec<RawCardSet>>,
     pub card_images: Vec<RawCardImage>,
     pub card_prices: Vec<RawCardPrice>,
+    pub banlist_info: Option<RawBanlistInfo>,
+    pub atk: Option<i32>,
+    pub def: Option<i32>,
+    pub level: Option<u32>,
+    pub attribute: Option<String>,
+    pub linkval: Option<u32>,
+    pub scale: Option<u32>,
 }
 #[derive(Debug, Deserialize, Clone)]
 pub struct RawCardSet {
@@ -41,6 +38,7 @@ pub struct RawCardImage {
     pub id: u32,
     pub image_url: String,
     pub image_url_small: String,
+    pub image_url_cropped: Option<String>,
 }
 #[derive(Debug, Deserialize, Clone)]
 pub struct RawCardPrice {
@@ -50,9 +48,15 @@ pub struct RawCardPrice {
     pub amazon_price: String,
     pub coolstuffinc_price: String,
 }
+#[derive(Debug, Deserialize, Clone)]
+pub struct RawBanlistInfo {
+    pub ban_tcg: Option<String>,
+    pub ban_ocg: Option<String>,
+    pub ban_goat: Option<String>,
+}
 
 // parsed form of RawYugiohCard
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct YugiohCard {
     pub id: u32,
     pub name: String,
@@ -63,9 +67,12 @@ pub struct YugiohCard {
     pub card_sets: Option<Vec<CardSet>>,
     pub card_image: CardImage,
     pub card_prices: CardPrice,
+    pub banlist: BanlistInfo,
+    // monster stats, None for spells and traps and for stats a card does not have, like def on link monsters
+    pub stats: MonsterStats,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CardSet {
     pub set_name: String,
     pub set_code: String,
@@ -74,152 +81,64 @@ pub struct CardSet {
     pub set_price: f32,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct CardImage {
     pub id: u32,
     pub small: YugiohImage,
     pub large: YugiohImage,
+    // just the artwork without the card frame, for views where a whole card would be too small to make out
+    pub cropped: YugiohImage,
 }
 
-impl CardImage {
-    pub fn check_promises(
-        &mut self,
-        ctx: &eframe::egui::Context,
-        promises: &mut Buffer<Promise<Result<RetainedImage, anyhow::Error>>>,
-        not_ready_is_err: bool,
-    ) -> [(bool, Result<Option<RetainedImage>, anyhow::Error>); 2] {
-        [self.small.check_promise(ctx, promises, not_ready_is_err), self.large.check_promise(ctx, promises, not_ready_is_err)]
-    }
-}
-
-#[derive(Clone)]
+// where to find one card image, what is done with it once it is downloaded is up to the frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct YugiohImage {
     pub id: u32,
     pub url: String,
-    pub image: Option<TextureId>,
-    pub promise_index: Option<usize>,
-    pub size: String,
+    pub size: ImageSize,
 }
 
 impl YugiohImage {
-    pub fn from_raw(url: String, id: u32, size: String) -> Self {
-        Self {
-            url,
-            image: None,
-            promise_index: None,
-            id,
-            size,
-        }
+    pub fn from_raw(url: String, id: u32, size: ImageSize) -> Self {
+        Self { url, id, size }
     }
+}
 
-    pub fn check_promise(
-        &mut self,
-        ctx: &eframe::egui::Context,
-        promises: &mut Buffer<Promise<Result<RetainedImage, anyhow::Error>>>,
-        not_ready_is_err: bool,
-    ) -> (bool, Result<Option<RetainedImage>, anyhow::Error>) {
-        if let Some(promise_index) = self.promise_index {
-            let im = promises.get_ref(promise_index);
-            if let Some(im) = im {
-                if im.poll().is_ready() {
-                    let im = promises.try_take(promise_index);
-                    if let Some(im) = im {
-                        let im = im.block_and_take();
-                        self.promise_index = None;
-                        if let Ok(im) = im {
-                            self.image = Some(im.texture_id(ctx));
-                            (true, Ok(Some(im)))
-                        } else {
-                            (true, Err(anyhow!("{:?}\n{:?}", im.err().unwrap(), "Image promise exists but was not fulfilled")))
-                        }
-                    } else {
-                        (true, Err(anyhow!("{:?}", "Image promise could not be taken")))
-                    }
-                } else if not_ready_is_err {
-                    (true, Err(anyhow!("{:?}", "Image promise is not ready")))
-                } else {
-                    (false, Ok(None))
-                }
-            } else {
-                self.promise_index = None;
-                (true, Err(anyhow!("{:?}", "Image promise does not exist")))
-            }
-        } else {
-            (false, Ok(None))
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ImageSize {
+    Small,
+    Large,
+    Cropped,
+}
+
+impl ImageSize {
+    pub const ALL: [ImageSize; 3] = [ImageSize::Small, ImageSize::Large, ImageSize::Cropped];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImageSize::Small => "small",
+            ImageSize::Large => "large",
+            ImageSize::Cropped => "cropped",
         }
     }
 
-    pub fn get_promise(&self, api_override: bool, cache_path_raw: PathBuf) -> Promise<Result<RetainedImage, anyhow::Error>> {
-        let cache_path = cache_path_raw.join(format!("{}.cache", self.id));
-        let url = self.url.clone();
-        let debug_name = format!("{}:{}", self.size, self.id);
-        Promise::spawn_thread(format!("{}:{}", self.size, self.id), move || {
-            let mut traceback = String::new();
-            let image_bytes: Result<Vec<u8>, anyhow::Error> = if api_override {
-                Err(anyhow::Error::msg("API override"))
-            } else {
-                let r = std::fs::read(cache_path.clone());
-                if let Ok(data) = r {
-                    Ok(data)
-                } else {
-                    Err(anyhow::Error::msg("Failed to read image"))
-                }
-            };
-            if let Ok(image_bytes) = image_bytes {
-                let image = RetainedImage::from_image_bytes(debug_name, &image_bytes[..]);
-                if let Ok(image) = image {
-                    Ok(image)
-                } else {
-                    let res = std::fs::remove_file(cache_path);
-                    if res.is_ok() {
-                        traceback.push_str("Failed to load image bytes\n");
-                    } else {
-                        traceback.push_str("Failed to load image bytes\nFailed to delete corrupted image from cache\n");
-                    }
-                    Err(anyhow::anyhow!(traceback))
-                }
-            } else {
-                traceback.push_str("Failed to read image from cache, attempting to load from api\n");
-                let image = reqwest::blocking::get(url.as_str());
-                if let Ok(image) = image {
-                    let image_bytes = image.bytes();
-                    if let Ok(image_bytes) = image_bytes {
-                        let raw_image_bytes = image_bytes.to_vec();
-                        let res = std::fs::write(cache_path, &raw_image_bytes);
-                        if let Err(res) = res {
-                            traceback.push_str(format!("Failed to write image to cache:{}\n", res).as_str());
-                        }
-                        let image = RetainedImage::from_image_bytes(debug_name, &raw_image_bytes.to_vec()[..]);
-                        if let Ok(image) = image {
-                            Ok(image)
-                        } else {
-                            traceback.push_str("Failed to load image bytes\n");
-                            Err(anyhow::anyhow!(traceback))
-                        }
-                    } else {
-                        traceback.push_str("Failed to read image bytes from api\n");
-                        Err(anyhow::anyhow!(traceback))
-                    }
-                } else {
-                    traceback.push_str("Failed to load image from api\n");
-                    Err(anyhow::anyhow!(traceback))
-                }
-            }
-        })
+    // the pixel size ygoprodeck serves each tier in
+    pub fn dimensions(&self) -> (u32, u32) {
+        match self {
+            ImageSize::Small => (168, 246),
+            ImageSize::Large => (421, 614),
+            ImageSize::Cropped => (624, 624),
+        }
     }
 }
 
-impl std::fmt::Debug for YugiohImage {
+impl std::fmt::Display for ImageSize {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("CardImage")
-            .field("url", &self.url)
-            .field("image", &self.image.is_some())
-            .field("promise_index", &self.promise_index)
-            .finish()
+        f.write_str(self.as_str())
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CardPrice {
     pub cardmarket_price: f32,
     pub tcgplayer_price: f32,
@@ -254,13 +173,35 @@ impl YugiohCard {
             card_sets,
             race: raw_card.race,
             archetype: raw_card.archetype.unwrap_or_else(|| "None".to_string()),
+            banlist: raw_card.banlist_info.map(BanlistInfo::from_raw).unwrap_or_default(),
+            stats: MonsterStats {
+                atk: raw_card.atk,
+                def: raw_card.def,
+                level: raw_card.level,
+                attribute: raw_card.attribute,
+                link: raw_card.linkval,
+                scale: raw_card.scale,
+            },
         }
     }
-    pub fn as_mut(&mut self) -> &mut Self {
-        self
+
+    // fusion, synchro, xyz and link monsters live in the extra deck
+    pub fn is_extra_deck(&self) -> bool {
+        ["Fusion", "Synchro", "XYZ", "Link"].iter().any(|t| self.card_type.contains(t))
     }
 }
 
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MonsterStats {
+    pub atk: Option<i32>,
+    pub def: Option<i32>,
+    // the rank for xyz monsters
+    pub level: Option<u32>,
+    pub attribute: Option<String>,
+    pub link: Option<u32>,
+    pub scale: Option<u32>,
+}
+
 impl CardSet {
     pub fn from_raw(raw_card_set: RawCardSet) -> Self {
         Self {
@@ -277,13 +218,64 @@ impl CardImage {
     pub fn from_raw(raw_card_image: RawCardImage) -> Self {
         Self {
             id: raw_card_image.id,
-            small: YugiohImage::from_raw(raw_card_image.image_url_small, raw_card_image.id, "small".to_owned()),
-            large: YugiohImage::from_raw(raw_card_image.image_url, raw_card_image.id, "large".to_owned()),
+            small: YugiohImage::from_raw(raw_card_image.image_url_small, raw_card_image.id, ImageSize::Small),
+            // older dumps of the api have no cropped art, the full card is the closest thing to it
+            cropped: YugiohImage::from_raw(
+                raw_card_image.image_url_cropped.unwrap_or_else(|| raw_card_image.image_url.clone()),
+                raw_card_image.id,
+                ImageSize::Cropped,
+            ),
+            large: YugiohImage::from_raw(raw_card_image.image_url, raw_card_image.id, ImageSize::Large),
+        }
+    }
+
+    pub fn get(&self, size: ImageSize) -> &YugiohImage {
+        match size {
+            ImageSize::Small => &self.small,
+            ImageSize::Large => &self.large,
+            ImageSize::Cropped => &self.cropped,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PriceVendor {
+    Cardmarket,
+    Tcgplayer,
+    Ebay,
+    Amazon,
+    Coolstuffinc,
+}
+
+impl PriceVendor {
+    pub const ALL: [PriceVendor; 5] = [PriceVendor::Cardmarket, PriceVendor::Tcgplayer, PriceVendor::Ebay, PriceVendor::Amazon, PriceVendor::Coolstuffinc];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PriceVendor::Cardmarket => "Cardmarket",
+            PriceVendor::Tcgplayer => "TCGplayer",
+            PriceVendor::Ebay => "eBay",
+            PriceVendor::Amazon => "Amazon",
+            PriceVendor::Coolstuffinc => "CoolStuffInc",
         }
     }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|vendor| vendor.name().eq_ignore_ascii_case(name))
+    }
 }
 
 impl CardPrice {
+    pub fn get(&self, vendor: PriceVendor) -> f32 {
+        match vendor {
+            PriceVendor::Cardmarket => self.cardmarket_price,
+            PriceVendor::Tcgplayer => self.tcgplayer_price,
+            PriceVendor::Ebay => self.ebay_price,
+            PriceVendor::Amazon => self.amazon_price,
+            PriceVendor::Coolstuffinc => self.coolstuffinc_price,
+        }
+    }
+
     pub fn from_raw(raw_card_price: RawCardPrice) -> Self {
         Self {
             cardmarket_price: raw_card_price.cardmarket_price.parse().unwrap_or(0.0),
@@ -295,152 +287,58 @@ impl CardPrice {
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct YugiohDeck {
-    pub main_deck: Vec<usize>,
-    pub extra_deck: Vec<usize>,
-    pub side_deck: Vec<usize>,
-    pub been_loaded: bool,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum BanStatus {
+    #[default]
+    Unlimited,
+    SemiLimited,
+    Limited,
+    Forbidden,
 }
 
-impl YugiohDeck {
-    pub fn new(been_loaded: bool) -> Self {
-        Self {
-            main_deck: Vec::new(),
-            extra_deck: Vec::new(),
-            side_deck: Vec::new(),
-            been_loaded,
-        }
-    }
-    pub fn from_file(path: PathBuf, cards: &[YugiohCard]) -> Self {
-        // try to open the file
-        let file = std::fs::File::open(path);
-        if let Ok(file) = file {
-            let mut current_deck = DeckType::None;
-            // read the file line by line
-            let mut reader = std::io::BufReader::new(file);
-            let mut main_deck = Vec::new();
-            let mut extra_deck = Vec::new();
-            let mut side_deck = Vec::new();
-            let mut resultish = 1;
-            while resultish != 0 {
-                let mut str = String::new();
-                let res = reader.read_line(&mut str);
-                if let Ok(res) = res {
-                    resultish = res;
-                    // parse the line as a u32
-                    let card_id = str.trim().parse::<u32>();
-                    if let Ok(card_id) = card_id {
-                        // get the index of the card in the cards vector
-                        let card_index = cards.iter().position(|card| card.id == card_id);
-                        if let Some(card_index) = card_index {
-                            // add the card to the deck
-                            match current_deck {
-                                DeckType::Main => main_deck.push(card_index),
-                                DeckType::Extra => extra_deck.push(card_index),
-                                DeckType::Side => side_deck.push(card_index),
-                                DeckType::None => (),
-                            }
-                        } else {
-                            eprintln!("Card with id {} not found", card_id);
-                        }
-                    } else {
-                        // match on the deck markers. if the line *contains* a deck marker, set the current deck to that deck
-                        if str.contains("#main") {
-                            current_deck = DeckType::Main;
-                        } else if str.contains("#extra") {
-                            current_deck = DeckType::Extra;
-                        } else if str.contains("!side") {
-                            current_deck = DeckType::Side;
-                        }
-                    }
-                } else {
-                    Self::new(true);
-                }
-            }
-            Self {
-                main_deck,
-                extra_deck,
-                side_deck,
-                been_loaded: true,
-            }
-        } else {
-            Self::new(true)
+impl BanStatus {
+    pub fn from_raw(raw: &str) -> Self {
+        match raw {
+            "Banned" | "Forbidden" => BanStatus::Forbidden,
+            "Limited" => BanStatus::Limited,
+            "Semi-Limited" => BanStatus::SemiLimited,
+            _ => BanStatus::Unlimited,
         }
     }
 
-    pub fn contains_card(&self, card: usize) -> DeckType {
-        if self.main_deck.contains(&card) {
-            DeckType::Main
-        } else if self.extra_deck.contains(&card) {
-            DeckType::Extra
-        } else if self.side_deck.contains(&card) {
-            DeckType::Side
-        } else {
-            DeckType::None
+    pub fn name(&self) -> &'static str {
+        match self {
+            BanStatus::Unlimited => "Unlimited",
+            BanStatus::SemiLimited => "Semi-Limited",
+            BanStatus::Limited => "Limited",
+            BanStatus::Forbidden => "Forbidden",
         }
     }
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum DeckType {
-    None,
-    Main,
-    Extra,
-    Side,
-}
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct YugiohCardSearchCriteria {
-    pub string: String,
-}
-
-impl YugiohCardSearchCriteria {
-    pub fn new() -> Self {
-        Self { string: String::new() }
+    pub fn max_copies(&self) -> usize {
+        match self {
+            BanStatus::Unlimited => 3,
+            BanStatus::SemiLimited => 2,
+            BanStatus::Limited => 1,
+            BanStatus::Forbidden => 0,
+        }
     }
 }
 
-impl YugiohCardSearchCriteria {
-    pub fn matches(self, card: &YugiohCard) -> bool {
-        // check if a card matches the criteria
-        // for the time being just check if the name contains the string
-        match_wild(self.string.to_lowercase(), card.name.to_lowercase())
-    }
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct BanlistInfo {
+    pub tcg: BanStatus,
+    pub ocg: BanStatus,
+    pub goat: BanStatus,
 }
 
-fn match_wild(wild: String, string: String) -> bool {
-    // if the string does not end with an !, add a wildcard to the end
-    let mut wild = wild;
-    if wild.ends_with('!') {
-        wild.pop();
-    } else {
-        wild.push('*');
-    }
-
-    if wild.starts_with('!') {
-        wild.remove(0);
-    } else {
-        wild.insert(0, '*');
-    }
-    // replace - in both strings with a space
-    let mut special_chars = vec!['-', '(', ')', '[', ']', '{', '}', '+', '.', '\\', '^', '$', '|', '"', '\'', '!'];
-    // if the wild string contains any of the special chars, remove them from the special chars list
-    for c in wild.chars() {
-        if special_chars.contains(&c) {
-            special_chars.retain(|&x| x != c);
+impl BanlistInfo {
+    pub fn from_raw(raw: RawBanlistInfo) -> Self {
+        let parse = |raw: Option<String>| raw.as_deref().map(BanStatus::from_raw).unwrap_or_default();
+        Self {
+            tcg: parse(raw.ban_tcg),
+            ocg: parse(raw.ban_ocg),
+            goat: parse(raw.ban_goat),
         }
     }
-    let wild = clean_string(wild, special_chars.clone());
-    let string = clean_string(string, special_chars);
-    // println!("wild: {} | string: {}", wild, string);
-    WildMatch::new(&wild).matches(&string)
-}
-
-fn clean_string(string: String, special_chars: Vec<char>) -> String {
-    let mut string = string;
-    for char in special_chars.iter() {
-        string = string.replace(*char, "");
-    }
-    string
 }